@@ -294,7 +294,7 @@ impl<E, D: Collection> EnumMap<E, D> {
     /// let map_ref: EnumMapRef<_, _> = map.as_ref();
     /// let values: Vec<(Sequential<U3>, &usize)> = Vec::from_iter(map_ref.into_iter());
     /// ```
-    pub fn as_ref(&self) -> EnumMapRef<E, D::Item> {
+    pub fn as_ref(&self) -> EnumMapRef<'_, E, D::Item> {
         EnumMapRef {
             data: &*self.data,
             __enum: PhantomData,
@@ -368,7 +368,7 @@ impl<E, D: CollectionMut> EnumMap<E, D> {
     /// let map_mut: EnumMapMut<_, _> = map.as_mut();
     /// let values: Vec<(Sequential<U3>, &mut usize)> = Vec::from_iter(map_mut.into_iter());
     /// ```
-    pub fn as_mut(&mut self) -> EnumMapMut<E, D::Item> {
+    pub fn as_mut(&mut self) -> EnumMapMut<'_, E, D::Item> {
         EnumMapMut {
             data: &mut *self.data,
             __enum: PhantomData,
@@ -551,10 +551,12 @@ impl<E: Enum, D: Collection> EnumMap<E, D> {
             .map(|(i, v)| (E::cast_from(i), v))
     }
 
+    /// Returns a new `EnumMapArray` containing the [`AsRef`] borrow of each value in this map.
     pub fn items_as_ref<T: ?Sized>(&self) -> EnumMapArray<E, &T> where D::Item: AsRef<T> {
         EnumMapArray::from_iter(self.data.iter().map(|v| v.as_ref()))
     }
-    
+
+    /// Returns a new `EnumMapArray` containing the dereferenced borrow of each value in this map.
     pub fn items_as_deref(&self) -> EnumMapArray<E, &<D::Item as Deref>::Target>
     where
         D::Item: Deref,
@@ -611,6 +613,8 @@ impl<E: Enum, D: CollectionMut> EnumMap<E, D> {
             .map(|(i, v)| (E::cast_from(i), v))
     }
 
+    /// Returns a new `EnumMapArray` containing the mutably dereferenced borrow of each value in
+    /// this map.
     pub fn items_as_deref_mut(&mut self) -> EnumMapArray<E, &mut <D::Item as Deref>::Target>
     where
         D::Item: DerefMut,
@@ -618,6 +622,7 @@ impl<E: Enum, D: CollectionMut> EnumMap<E, D> {
         EnumMapArray::from_iter(self.data.iter_mut().map(|v| v.deref_mut()))
     }
 
+    /// Returns a new `EnumMapArray` containing the [`AsMut`] borrow of each value in this map.
     pub fn items_as_mut<T: ?Sized>(&mut self) -> EnumMapArray<E, &mut T> where D::Item: AsMut<T> {
         EnumMapArray::from_iter(self.data.iter_mut().map(|v| v.as_mut()))
     }
@@ -667,6 +672,9 @@ impl<E: Enum, T> EnumMapArray<E, T> {
 }
 
 impl<'a, E, T> EnumMapRef<'a, E, T> {
+    /// Creates a new `EnumMapRef` borrowing the given slice.
+    ///
+    /// The slice is expected to contain one value per enum variant, in order.
     pub const fn from_slice(slice: &'a [T]) -> Self {
         Self {
             data: slice,
@@ -676,6 +684,9 @@ impl<'a, E, T> EnumMapRef<'a, E, T> {
 }
 
 impl<'a, E, T> EnumMapMut<'a, E, T> {
+    /// Creates a new `EnumMapMut` mutably borrowing the given slice.
+    ///
+    /// The slice is expected to contain one value per enum variant, in order.
     pub fn from_slice_mut(slice: &'a mut [T]) -> Self {
         Self {
             data: slice,
@@ -683,3 +694,324 @@ impl<'a, E, T> EnumMapMut<'a, E, T> {
         }
     }
 }
+
+/// A map that uses an enum type `E` as keys, where each variant may or may not have an associated
+/// value.
+///
+/// `EnumPartialMap` is useful when only some of the variants carry data, for example "only these
+/// parameters changed during this block", or when storing per-voice optional data. It avoids the
+/// need for sentinel values in an [`EnumMapArray`], and allows iterating over the present entries
+/// only.
+///
+/// Storage is fixed-size and allocated inline, which makes it usable in real-time contexts.
+///
+/// # Example
+/// ```rust
+/// use clogbox_core::r#enum::enum_map::EnumPartialMap;
+/// use clogbox_derive::Enum;
+///
+/// #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Enum)]
+/// enum Param {
+///     Cutoff,
+///     Resonance,
+///     Drive,
+/// }
+///
+/// let mut changed = EnumPartialMap::new();
+/// changed.insert(Param::Cutoff, 440.0);
+/// changed.insert(Param::Drive, 2.0);
+///
+/// assert_eq!(2, changed.len());
+/// assert_eq!(Some(&440.0), changed.get(Param::Cutoff));
+/// assert_eq!(None, changed.get(Param::Resonance));
+/// assert_eq!(
+///     vec![(Param::Cutoff, &440.0), (Param::Drive, &2.0)],
+///     Vec::from_iter(changed.iter())
+/// );
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EnumPartialMap<E: Enum, T> {
+    data: EnumMapArray<E, Option<T>>,
+}
+
+impl<E: Enum, T> Default for EnumPartialMap<E, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Enum, T> EnumPartialMap<E, T> {
+    /// Creates a new, empty `EnumPartialMap`.
+    ///
+    /// # Example
+    /// ```rust
+    /// use typenum::U3;
+    /// use clogbox_core::r#enum::enum_map::EnumPartialMap;
+    /// use clogbox_core::r#enum::Sequential;
+    ///
+    /// let map = EnumPartialMap::<Sequential<U3>, f32>::new();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            data: EnumMapArray::new(|_| None),
+        }
+    }
+
+    /// Inserts a value for the given enum variant, returning the previous value if there was one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use typenum::U3;
+    /// use clogbox_core::r#enum::enum_map::EnumPartialMap;
+    /// use clogbox_core::r#enum::seq;
+    ///
+    /// let mut map = EnumPartialMap::new();
+    /// assert_eq!(None, map.insert(seq::<U3>(1), 1.0));
+    /// assert_eq!(Some(1.0), map.insert(seq::<U3>(1), 2.0));
+    /// ```
+    pub fn insert(&mut self, key: E, value: T) -> Option<T> {
+        self.data[key].replace(value)
+    }
+
+    /// Removes the value for the given enum variant, returning it if there was one.
+    ///
+    /// # Example
+    /// ```rust
+    /// use typenum::U3;
+    /// use clogbox_core::r#enum::enum_map::EnumPartialMap;
+    /// use clogbox_core::r#enum::seq;
+    ///
+    /// let mut map = EnumPartialMap::new();
+    /// map.insert(seq::<U3>(0), 1.0);
+    /// assert_eq!(Some(1.0), map.remove(seq(0)));
+    /// assert_eq!(None, map.remove(seq(0)));
+    /// ```
+    pub fn remove(&mut self, key: E) -> Option<T> {
+        self.data[key].take()
+    }
+
+    /// Returns a reference to the value associated with the given enum variant, if present.
+    pub fn get(&self, key: E) -> Option<&T> {
+        self.data[key].as_ref()
+    }
+
+    /// Returns a mutable reference to the value associated with the given enum variant, if
+    /// present.
+    pub fn get_mut(&mut self, key: E) -> Option<&mut T> {
+        self.data[key].as_mut()
+    }
+
+    /// Returns a mutable reference to the value associated with the given enum variant, inserting
+    /// the result of `fill` first if no value is present.
+    ///
+    /// # Example
+    /// ```rust
+    /// use typenum::U3;
+    /// use clogbox_core::r#enum::enum_map::EnumPartialMap;
+    /// use clogbox_core::r#enum::seq;
+    ///
+    /// let mut map = EnumPartialMap::new();
+    /// *map.get_or_insert_with(seq::<U3>(2), || 0) += 1;
+    /// *map.get_or_insert_with(seq::<U3>(2), || 0) += 1;
+    /// assert_eq!(Some(&2), map.get(seq(2)));
+    /// ```
+    pub fn get_or_insert_with(&mut self, key: E, fill: impl FnOnce() -> T) -> &mut T {
+        self.data[key].get_or_insert_with(fill)
+    }
+
+    /// Returns `true` if a value is present for the given enum variant.
+    pub fn contains_key(&self, key: E) -> bool {
+        self.data[key].is_some()
+    }
+
+    /// Returns the number of enum variants that currently have a value.
+    pub fn len(&self) -> usize {
+        self.data.values().filter(|v| v.is_some()).count()
+    }
+
+    /// Returns `true` if no enum variant currently has a value.
+    pub fn is_empty(&self) -> bool {
+        self.data.values().all(|v| v.is_none())
+    }
+
+    /// Removes all values from the map.
+    pub fn clear(&mut self) {
+        for value in self.data.values_mut() {
+            value.take();
+        }
+    }
+
+    /// Returns an iterator over the present entries, yielding pairs of enum variants and
+    /// references to their values, in variant order.
+    pub fn iter(&self) -> impl Iterator<Item = (E, &T)> {
+        self.data
+            .iter()
+            .filter_map(|(k, v)| v.as_ref().map(|v| (k, v)))
+    }
+
+    /// Returns an iterator over the present entries, yielding pairs of enum variants and mutable
+    /// references to their values, in variant order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (E, &mut T)> {
+        self.data
+            .iter_mut()
+            .filter_map(|(k, v)| v.as_mut().map(|v| (k, v)))
+    }
+
+    /// Returns an iterator over the enum variants that currently have a value.
+    pub fn keys(&self) -> impl Iterator<Item = E> + '_ {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// Returns an iterator over the present values, in variant order.
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.data.values().filter_map(Option::as_ref)
+    }
+
+    /// Returns a view of the map as a full [`EnumMapArray`] of optional values.
+    pub fn as_options(&self) -> &EnumMapArray<E, Option<T>> {
+        &self.data
+    }
+
+    /// Returns a mutable view of the map as a full [`EnumMapArray`] of optional values.
+    pub fn as_options_mut(&mut self) -> &mut EnumMapArray<E, Option<T>> {
+        &mut self.data
+    }
+
+    /// Converts this partial map into a full [`EnumMapArray`], using `fill` to create values for
+    /// the variants which are not present.
+    ///
+    /// # Example
+    /// ```rust
+    /// use typenum::U3;
+    /// use clogbox_core::r#enum::enum_map::EnumPartialMap;
+    /// use clogbox_core::r#enum::seq;
+    ///
+    /// let mut map = EnumPartialMap::new();
+    /// map.insert(seq::<U3>(1), 1.0);
+    /// let full = map.into_full(|_| 0.0);
+    /// assert_eq!(&[0.0, 1.0, 0.0], full.as_slice());
+    /// ```
+    pub fn into_full(self, mut fill: impl FnMut(E) -> T) -> EnumMapArray<E, T> {
+        EnumMapArray::from_iter(
+            self.data
+                .into_iter()
+                .map(|(k, v)| v.unwrap_or_else(|| fill(k))),
+        )
+    }
+}
+
+impl<E: Enum, T> From<EnumMapArray<E, T>> for EnumPartialMap<E, T> {
+    fn from(value: EnumMapArray<E, T>) -> Self {
+        Self {
+            data: value.map(|_, v| Some(v)),
+        }
+    }
+}
+
+impl<E: Enum, T> From<EnumMapArray<E, Option<T>>> for EnumPartialMap<E, T> {
+    fn from(data: EnumMapArray<E, Option<T>>) -> Self {
+        Self { data }
+    }
+}
+
+impl<E: Enum, T> FromIterator<(E, T)> for EnumPartialMap<E, T> {
+    fn from_iter<I: IntoIterator<Item = (E, T)>>(iter: I) -> Self {
+        let mut this = Self::new();
+        this.extend(iter);
+        this
+    }
+}
+
+impl<E: Enum, T> Extend<(E, T)> for EnumPartialMap<E, T> {
+    fn extend<I: IntoIterator<Item = (E, T)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<E: Enum, T> IntoIterator for EnumPartialMap<E, T> {
+    type Item = (E, T);
+    type IntoIter = std::iter::FilterMap<
+        <EnumMapArray<E, Option<T>> as IntoIterator>::IntoIter,
+        fn((E, Option<T>)) -> Option<(E, T)>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.data.into_iter().filter_map(|(k, v)| v.map(|v| (k, v)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#enum::{seq, Sequential};
+    use az::Cast;
+    use rstest::rstest;
+    use typenum::U4;
+
+    type Key = Sequential<U4>;
+
+    #[rstest]
+    #[case(&[])]
+    #[case(&[2])]
+    #[case(&[0, 3])]
+    #[case(&[3, 1, 0, 2])]
+    fn test_partial_map_insert_get_iter(#[case] keys: &[usize]) {
+        let mut map = EnumPartialMap::<Key, usize>::new();
+        for &k in keys {
+            assert_eq!(None, map.insert(seq(k), 10 * k));
+        }
+        assert_eq!(keys.len(), map.len());
+        assert_eq!(keys.is_empty(), map.is_empty());
+        for k in 0..4 {
+            assert_eq!(keys.contains(&k), map.contains_key(seq(k)));
+            assert_eq!(
+                keys.contains(&k).then_some(10 * k),
+                map.get(seq(k)).copied()
+            );
+        }
+
+        // Iteration only yields present entries, in variant order
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        assert_eq!(
+            Vec::from_iter(sorted.iter().map(|&k| (k, 10 * k))),
+            Vec::from_iter(map.iter().map(|(k, &v)| (k.cast(), v)))
+        );
+        assert_eq!(sorted, Vec::from_iter(map.keys().map(Cast::cast)));
+        assert_eq!(map, EnumPartialMap::from_iter(map.clone()));
+    }
+
+    #[rstest]
+    #[case(0, Some(1))]
+    #[case(1, None)]
+    #[case(2, Some(3))]
+    fn test_partial_map_remove(#[case] key: usize, #[case] expected: Option<usize>) {
+        let mut map = EnumPartialMap::<Key, usize>::from_iter([(seq(0), 1), (seq(2), 3)]);
+        assert_eq!(expected, map.remove(seq(key)));
+        assert_eq!(None, map.get(seq(key)));
+        assert_eq!(None, map.remove(seq(key)));
+        assert_eq!(2 - expected.is_some() as usize, map.len());
+    }
+
+    #[rstest]
+    fn test_partial_map_replace_and_full_roundtrip() {
+        let mut map = EnumPartialMap::<Key, usize>::new();
+        assert_eq!(None, map.insert(seq(1), 1));
+        assert_eq!(Some(1), map.insert(seq(1), 2));
+        *map.get_or_insert_with(seq(3), || 0) += 5;
+        assert_eq!(vec![(seq(1), &2), (seq(3), &5)], Vec::from_iter(map.iter()));
+
+        let full = map.clone().into_full(|k| 100 + k.cast());
+        assert_eq!(&[100, 2, 102, 5], full.as_slice());
+        let restored = EnumPartialMap::from(full);
+        assert_eq!(4, restored.len());
+        assert_eq!(Some(&2), restored.get(seq(1)));
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(0, map.into_iter().count());
+    }
+}
@@ -31,6 +31,7 @@ use std::cmp::Ordering;
 use std::marker::PhantomData;
 use std::ops;
 use std::ops::{Deref, DerefMut};
+use std::sync::OnceLock;
 use typenum::{Prod, Unsigned, U0};
 pub use az;
 
@@ -69,6 +70,9 @@ pub trait Enum: Copy + Send + Eq + Ord + Cast<usize> + CastFrom<usize> {
     /// This can be used for debugging, logging, or display purposes, allowing
     /// the enum's variant to be converted to a human-readable string.
     ///
+    /// Enums implementing this trait through `#[derive(Enum)]` return borrowed names, and also
+    /// provide an inherent `name_static` method returning a `&'static str`.
+    ///
     /// # Example
     /// ```rust
     /// use clogbox_core::r#enum::Enum;
@@ -82,9 +86,10 @@ pub trait Enum: Copy + Send + Eq + Ord + Cast<usize> + CastFrom<usize> {
     /// let color = Color::Red;
     /// assert_eq!(color.name(), "Red");
     /// ```
-    fn name(&self) -> Cow<str>;
+    fn name(&self) -> Cow<'_, str>;
 }
 
+/// Wrapper around a `usize`-indexed collection, allowing it to be indexed by an [`Enum`] instead.
 #[derive(Debug, Copy, Clone)]
 #[repr(transparent)]
 pub struct EnumIndex<C, E>(C, PhantomData<E>);
@@ -124,6 +129,7 @@ impl<E: Cast<usize>, C: ops::IndexMut<usize>> ops::IndexMut<E> for EnumIndex<C,
 }
 
 impl<C, E> EnumIndex<C, E> {
+    /// Consumes the `EnumIndex` and returns the wrapped collection.
     pub fn into_inner(self) -> C {
         self.0
     }
@@ -148,7 +154,7 @@ impl CastFrom<usize> for Empty {
 impl Enum for Empty {
     type Count = U0;
 
-    fn name(&self) -> Cow<str> {
+    fn name(&self) -> Cow<'_, str> {
         unreachable!()
     }
 }
@@ -158,6 +164,30 @@ pub fn enum_iter<E: Enum>() -> impl Iterator<Item = E> {
     (0..E::Count::USIZE).map(|i| E::cast_from(i))
 }
 
+/// Returns the variant of the given enum at the given index, clamped to the valid range.
+///
+/// This is useful to convert integer parameter values, which can be out of range, into enum
+/// variants.
+///
+/// # Panics
+///
+/// Panics if the enum has no variants.
+///
+/// # Example
+///
+/// ```rust
+/// use clogbox_core::r#enum::{from_index_clamped, seq, Sequential};
+/// use typenum::U3;
+///
+/// assert_eq!(seq::<U3>(1), from_index_clamped(1));
+/// assert_eq!(seq::<U3>(0), from_index_clamped(-4));
+/// assert_eq!(seq::<U3>(2), from_index_clamped(10));
+/// ```
+pub fn from_index_clamped<E: Enum>(index: i64) -> E {
+    let max = E::Count::USIZE - 1;
+    E::cast_from(usize::try_from(index).map_or(0, |index| index.min(max)))
+}
+
 /// A wrapper type representing a sequential index with a compile-time known size.
 ///
 /// `Sequential<N>` is a type-safe struct used to track an index at runtime (`usize`)
@@ -253,11 +283,24 @@ impl<N: Unsigned> PartialOrd<Self> for Sequential<N> {
     }
 }
 
+/// Number of [`Sequential`] names that are formatted once and cached; names for indices
+/// past this are formatted on each call.
+const SEQUENTIAL_CACHED_NAMES: usize = 256;
+
 impl<N: Send + Unsigned + ArrayLength> Enum for Sequential<N> {
     type Count = N;
 
-    fn name(&self) -> Cow<str> {
-        Cow::Owned(format!("{}", 1 + self.1))
+    fn name(&self) -> Cow<'_, str> {
+        static NAMES: OnceLock<Box<[String]>> = OnceLock::new();
+        let names = NAMES.get_or_init(|| {
+            (1..=SEQUENTIAL_CACHED_NAMES)
+                .map(|i| format!("{i}"))
+                .collect()
+        });
+        match names.get(self.1) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("{}", 1 + self.1)),
+        }
     }
 }
 
@@ -298,15 +341,15 @@ pub struct CartesianProduct<A, B>(pub A, pub B);
 
 impl<A: Enum, B: Enum> CastFrom<usize> for CartesianProduct<A, B> {
     fn cast_from(src: usize) -> Self {
-        let src_a = src / A::Count::USIZE;
-        let src_b = src % A::Count::USIZE;
+        let src_a = src / B::Count::USIZE;
+        let src_b = src % B::Count::USIZE;
         Self(A::cast_from(src_a), B::cast_from(src_b))
     }
 }
 
 impl<A: Enum, B: Enum> Cast<usize> for CartesianProduct<A, B> {
     fn cast(self) -> usize {
-        self.0.cast() * A::Count::USIZE + self.1.cast()
+        self.0.cast() * B::Count::USIZE + self.1.cast()
     }
 }
 
@@ -317,8 +360,33 @@ where
 {
     type Count = Prod<A::Count, B::Count>;
 
-    fn name(&self) -> Cow<str> {
+    fn name(&self) -> Cow<'_, str> {
         Cow::Owned(format!("{}:{}", self.0.name(), self.1.name()))
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use typenum::{U2, U3};
+
+    #[test]
+    fn test_cartesian_product_cast_unequal_counts() {
+        type Product = CartesianProduct<Sequential<U2>, Sequential<U3>>;
+        assert_eq!(6, <Product as Enum>::Count::USIZE);
+        for i in 0..6 {
+            let product = Product::cast_from(i);
+            assert_eq!(i / 3, product.0.cast());
+            assert_eq!(i % 3, product.1.cast());
+            assert_eq!(i, product.cast());
+        }
+    }
+
+    #[test]
+    fn test_from_index_clamped() {
+        let indices = [i64::MIN, -1, 0, 1, 2, 3, i64::MAX];
+        let variants = indices.map(|i| from_index_clamped::<Sequential<U3>>(i).cast());
+        assert_eq!([0, 0, 0, 1, 2, 2, 2], variants);
+    }
+}
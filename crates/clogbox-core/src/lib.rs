@@ -1,5 +1,11 @@
 #![warn(missing_docs)]
+//! Core traits and data structures of clogbox.
+//!
+//! This crate provides the [`Module`](module::Module) abstraction used to write audio processing
+//! blocks, strongly-typed enum indexing through [`Enum`](r#enum::Enum) and
+//! [`EnumMap`](r#enum::enum_map::EnumMap), parameter handling, and supporting math utilities.
 pub mod module;
 pub mod r#enum;
 pub mod param;
-pub mod math;
\ No newline at end of file
+pub mod math;
+pub mod thread;
@@ -6,6 +6,7 @@
 //! use clogbox_core::math::dsp::freq_to_z;
 //! let z = freq_to_z(44100.0, 1000.0);
 //! ```
+use az::CastFrom;
 use num_complex::Complex;
 use num_traits::{Float, FloatConst};
 
@@ -33,3 +34,179 @@ pub fn freq_to_z<T: Float + FloatConst>(sample_rate: T, f: T) -> Complex<T>
     let jw = Complex::new(T::zero(), T::TAU() * f / sample_rate);
     jw.exp()
 }
+
+/// Tuning reference used to convert between MIDI note numbers and frequencies, in 12-tone equal
+/// temperament.
+///
+/// The reference is the frequency of A4 (MIDI note 69), which defaults to 440 Hz, and is kept
+/// within [`Tuning::MIN_A4`]..[`Tuning::MAX_A4`] so that conversions stay finite.
+///
+/// # Example
+///
+/// ```
+/// use clogbox_core::math::dsp::Tuning;
+/// let tuning = Tuning::new(432.0);
+/// assert_eq!(432.0, tuning.note_to_freq(69.0));
+/// assert_eq!(864.0, tuning.note_to_freq(81.0));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Tuning {
+    a4: f64,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self::new(Self::STANDARD_A4)
+    }
+}
+
+impl Tuning {
+    /// Standard tuning reference frequency of A4, in Hz.
+    pub const STANDARD_A4: f64 = 440.0;
+    /// MIDI note number of A4.
+    pub const A4_NOTE: f64 = 69.0;
+    /// Lowest reference frequency of A4 that can be set, in Hz.
+    pub const MIN_A4: f64 = 20.0;
+    /// Highest reference frequency of A4 that can be set, in Hz.
+    pub const MAX_A4: f64 = 20000.0;
+
+    /// Creates a new tuning reference with the provided frequency for A4, in Hz. The frequency is
+    /// clamped to [`Self::MIN_A4`]..[`Self::MAX_A4`], and NaN falls back to
+    /// [`Self::STANDARD_A4`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::math::dsp::Tuning;
+    /// assert_eq!(Tuning::MIN_A4, Tuning::new(-1.0).a4());
+    /// assert_eq!(Tuning::MAX_A4, Tuning::new(f64::INFINITY).a4());
+    /// assert_eq!(Tuning::default(), Tuning::new(f64::NAN));
+    /// ```
+    pub fn new(a4: f64) -> Self {
+        let mut tuning = Self {
+            a4: Self::STANDARD_A4,
+        };
+        tuning.set_a4(a4);
+        tuning
+    }
+
+    /// Returns the reference frequency of A4, in Hz.
+    pub fn a4(&self) -> f64 {
+        self.a4
+    }
+
+    /// Sets the reference frequency of A4, in Hz, clamped to [`Self::MIN_A4`]..[`Self::MAX_A4`].
+    /// NaN values are ignored.
+    pub fn set_a4(&mut self, a4: f64) {
+        if !a4.is_nan() {
+            self.a4 = a4.clamp(Self::MIN_A4, Self::MAX_A4);
+        }
+    }
+
+    /// Converts a (possibly fractional) MIDI note number into a frequency, in Hz.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::math::dsp::Tuning;
+    /// assert_eq!(220.0, Tuning::default().note_to_freq(57.0f32));
+    /// ```
+    #[inline]
+    pub fn note_to_freq<T: Float + CastFrom<f64>>(&self, note: T) -> T {
+        let semitones = note - T::cast_from(Self::A4_NOTE);
+        T::cast_from(self.a4) * (semitones / T::cast_from(12.0)).exp2()
+    }
+
+    /// Converts a frequency, in Hz, into a fractional MIDI note number.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::math::dsp::Tuning;
+    /// assert_eq!(81.0, Tuning::default().freq_to_note(880.0));
+    /// ```
+    #[inline]
+    pub fn freq_to_note<T: Float + CastFrom<f64>>(&self, freq: T) -> T {
+        T::cast_from(Self::A4_NOTE) + T::cast_from(12.0) * (freq / T::cast_from(self.a4)).log2()
+    }
+}
+
+/// Converts a level in decibels into a linear gain.
+///
+/// # Example
+///
+/// ```
+/// use clogbox_core::math::dsp::db_to_linear;
+/// assert_eq!(1.0, db_to_linear(0.0));
+/// assert_eq!(0.1, db_to_linear(-20.0));
+/// ```
+#[inline]
+pub fn db_to_linear<T: Float + CastFrom<f64>>(db: T) -> T {
+    T::cast_from(10.0).powf(db / T::cast_from(20.0))
+}
+
+/// Converts a linear gain into a level in decibels. Silence returns negative infinity.
+///
+/// # Example
+///
+/// ```
+/// use clogbox_core::math::dsp::linear_to_db;
+/// assert_eq!(-20.0, linear_to_db(0.1));
+/// assert_eq!(f32::NEG_INFINITY, linear_to_db(0.0f32));
+/// ```
+#[inline]
+pub fn linear_to_db<T: Float + CastFrom<f64>>(gain: T) -> T {
+    T::cast_from(20.0) * gain.abs().log10()
+}
+
+/// Flushes subnormal (denormal) values to zero.
+///
+/// Subnormal values typically appear in the decaying tails of feedback paths, and are processed
+/// much more slowly than normal values by most CPUs.
+///
+/// # Example
+///
+/// ```
+/// use clogbox_core::math::dsp::flush_denormal;
+/// assert_eq!(0.0, flush_denormal(1e-40f32));
+/// assert_eq!(1e-30, flush_denormal(1e-30f32));
+/// ```
+#[inline]
+pub fn flush_denormal<T: Float>(x: T) -> T {
+    if x.is_subnormal() {
+        T::zero()
+    } else {
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(440.0, 440.0)]
+    #[case(0.0, Tuning::MIN_A4)]
+    #[case(-440.0, Tuning::MIN_A4)]
+    #[case(1e9, Tuning::MAX_A4)]
+    #[case(f64::INFINITY, Tuning::MAX_A4)]
+    #[case(f64::NEG_INFINITY, Tuning::MIN_A4)]
+    #[case(f64::NAN, Tuning::STANDARD_A4)]
+    fn test_tuning_reference_is_clamped(#[case] a4: f64, #[case] expected: f64) {
+        let tuning = Tuning::new(a4);
+        assert_eq!(expected, tuning.a4());
+        let freq = tuning.note_to_freq(60.0);
+        assert!(freq.is_finite() && freq > 0.0);
+        assert!(tuning.freq_to_note(freq).is_finite());
+    }
+
+    #[rstest]
+    fn test_tuning_set_a4_ignores_nan() {
+        let mut tuning = Tuning::new(432.0);
+        tuning.set_a4(f64::NAN);
+        assert_eq!(432.0, tuning.a4());
+        tuning.set_a4(415.0);
+        assert_eq!(415.0, tuning.a4());
+    }
+}
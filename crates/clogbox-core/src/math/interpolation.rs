@@ -126,6 +126,36 @@ fn cubic_interpolate<T: Copy + CastFrom<f64> + Num>(p: [T; 4], x: T) -> T {
                 + x * (3.0 * (p[1] - p[2]) + p[3] - p[0])))
 }
 
+/// Evaluates a cubic Hermite segment between two points at the relative position `x` (in `0..1`).
+///
+/// # Arguments
+///
+/// * `p0` - Value at the start of the segment.
+/// * `m0` - Tangent at the start of the segment, scaled by the segment length.
+/// * `p1` - Value at the end of the segment.
+/// * `m1` - Tangent at the end of the segment, scaled by the segment length.
+/// * `x` - Relative position within the segment.
+///
+/// # Examples
+/// ```
+/// use clogbox_core::math::interpolation::hermite_interpolate;
+///
+/// // Straight line from 0 to 1
+/// assert_eq!(0.5, hermite_interpolate(0.0, 1.0, 1.0, 1.0, 0.5));
+/// // Smoothstep
+/// assert_eq!(0.5, hermite_interpolate(0.0, 0.0, 1.0, 0.0, 0.5));
+/// ```
+#[replace_float_literals(T::cast_from(literal))]
+pub fn hermite_interpolate<T: Copy + CastFrom<f64> + Num>(p0: T, m0: T, p1: T, m1: T, x: T) -> T {
+    let x2 = x * x;
+    let x3 = x2 * x;
+    let h00 = 2.0 * x3 - 3.0 * x2 + 1.0;
+    let h10 = x3 - 2.0 * x2 + x;
+    let h01 = 3.0 * x2 - 2.0 * x3;
+    let h11 = x3 - x2;
+    h00 * p0 + h10 * m0 + h01 * p1 + h11 * m1
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -56,7 +56,7 @@
 //!
 //! let mut my_module = Inverter::<f32, Sequential<U1>>::default();
 //! let block_size = 128;
-//! let stream_data = StreamData { sample_rate: 44100.0 ,bpm: 120. ,block_size };
+//! let stream_data = StreamData { sample_rate: 44100.0 ,bpm: 120. ,block_size, transport: Default::default(), is_offline: false };
 //! let inputs = (0..block_size).map(|i| i as f32).collect::<Vec<_>>();
 //! let mut outputs = vec![0.0; block_size];
 //! my_module.process(&stream_data, &[&inputs], &mut [&mut outputs]);
@@ -64,13 +64,11 @@
 //! ```
 pub mod analysis;
 pub mod sample;
+pub mod stereo;
 pub mod utilitarian;
 
 use crate::r#enum::enum_map::EnumMapArray;
-use crate::r#enum::{Enum, EnumIndex};
-use az::Cast;
-use std::marker::PhantomData;
-use std::ops;
+use crate::r#enum::{enum_iter, Enum};
 use typenum::Unsigned;
 
 /// Represents the metadata and configuration for a stream of audio data.
@@ -82,9 +80,94 @@ pub struct StreamData {
     pub bpm: f64,
     /// The size of a processing block in samples.
     pub block_size: usize,
+    /// The state of the host transport at the start of the processing block.
+    pub transport: Transport,
+    /// Whether the stream is rendered offline (e.g. when bouncing), in which case modules can use
+    /// higher-quality algorithms at the expense of real-time performance.
+    pub is_offline: bool,
+}
+
+/// Time signature of the host transport.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TimeSignature {
+    /// Number of beats in a bar.
+    pub numerator: u16,
+    /// Note value of a beat, as a fraction of a whole note.
+    pub denominator: u16,
+}
+
+/// The state of the host transport, provided to modules through [`StreamData`] so that they can
+/// synchronize to the playhead.
+///
+/// All positions are given in beats (quarter notes), at the start of the processing block.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transport {
+    /// Whether the host transport is playing.
+    pub playing: bool,
+    /// Whether the host transport is looping.
+    pub looping: bool,
+    /// Position of the playhead, in beats.
+    pub position: f64,
+    /// Position of the start of the current bar, in beats.
+    pub bar_start: f64,
+    /// Start of the loop range, in beats.
+    pub loop_start: f64,
+    /// End of the loop range, in beats.
+    pub loop_end: f64,
+    /// Current time signature.
+    pub time_signature: TimeSignature,
+}
+
+impl Transport {
+    /// A stopped transport at the start of the timeline, in 4/4. This is the transport used when
+    /// the host does not provide one.
+    pub const STOPPED: Self = Self {
+        playing: false,
+        looping: false,
+        position: 0.,
+        bar_start: 0.,
+        loop_start: 0.,
+        loop_end: 0.,
+        time_signature: TimeSignature {
+            numerator: 4,
+            denominator: 4,
+        },
+    };
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Self::STOPPED
+    }
 }
 
 impl StreamData {
+    /// Creates stream metadata for a realtime stream, with a stopped transport.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The sample rate of the audio stream, in samples per second.
+    /// * `bpm` - The tempo of the audio stream, in beats per minute.
+    /// * `block_size` - The size of a processing block in samples.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::module::{StreamData, Transport};
+    /// let stream_data = StreamData::new(44100.0, 120.0, 512);
+    /// assert_eq!(Transport::STOPPED, stream_data.transport);
+    /// assert!(!stream_data.is_offline);
+    /// ```
+    pub const fn new(sample_rate: f64, bpm: f64, block_size: usize) -> Self {
+        Self {
+            sample_rate,
+            bpm,
+            block_size,
+            transport: Transport::STOPPED,
+            is_offline: false,
+        }
+    }
+
     /// Calculates the time duration of one sample in seconds.
     ///
     /// # Returns
@@ -100,6 +183,8 @@ impl StreamData {
     ///     sample_rate: 44100.0,
     ///     bpm: 120.0,
     ///     block_size: 512,
+    ///     transport: Default::default(),
+    ///     is_offline: false,
     /// };
     /// let time_duration = stream_data.dt();
     /// assert_eq!(1./44100., time_duration);
@@ -126,6 +211,8 @@ impl StreamData {
     ///     sample_rate: 44100.0,
     ///     bpm: 120.0,
     ///     block_size: 512,
+    ///     transport: Default::default(),
+    ///     is_offline: false,
     /// };
     /// let beats = 4.0;
     /// let length = stream_data.beat_length(beats);
@@ -145,6 +232,32 @@ impl StreamData {
     pub fn beat_sample_length(&self, beats: f64) -> f64 {
         self.sample_rate * self.beat_length(beats)
     }
+
+    /// Calculates the position of the playhead at a given sample of the processing block, in beats.
+    ///
+    /// # Arguments
+    /// * `sample` - Index of the sample within the processing block.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::module::{StreamData, Transport};
+    /// let stream_data = StreamData {
+    ///     sample_rate: 44100.0,
+    ///     bpm: 120.0,
+    ///     block_size: 512,
+    ///     transport: Transport {
+    ///         playing: true,
+    ///         position: 8.0,
+    ///         ..Transport::STOPPED
+    ///     },
+    ///     is_offline: false,
+    /// };
+    /// assert_eq!(9.0, stream_data.beat_position(22050));
+    /// ```
+    pub fn beat_position(&self, sample: usize) -> f64 {
+        self.transport.position + sample as f64 / self.beat_sample_length(1.)
+    }
 }
 
 /// A trait representing a raw module with audio processing capabilities.
@@ -159,6 +272,20 @@ pub trait RawModule: Send {
     /// Returns the number of outputs of the module.
     fn outputs(&self) -> usize;
 
+    /// Returns a description of the inputs and outputs of the module, which can be used to display
+    /// the module without knowing its concrete type.
+    ///
+    /// The default implementation numbers the ports from 1, as
+    /// [`Sequential`](crate::r#enum::Sequential) does; modules implementing [`Module`] report the
+    /// names of their `Inputs` and `Outputs` enums instead.
+    fn info(&self) -> ModuleInfo {
+        ModuleInfo {
+            sample_type: std::any::type_name::<Self::Sample>(),
+            inputs: ModuleInfo::numbered_ports(self.inputs()),
+            outputs: ModuleInfo::numbered_ports(self.outputs()),
+        }
+    }
+
     /// Checks if the module supports the given stream data.
     ///
     /// # Arguments
@@ -193,6 +320,81 @@ pub trait RawModule: Send {
     ) -> ProcessStatus;
 }
 
+/// Description of a single input or output port of a module.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PortInfo {
+    /// Index of the port, which is the position of its buffer in the process call.
+    pub index: usize,
+    /// Display name of the port.
+    pub name: String,
+}
+
+/// Runtime description of the ports of a module, gathered from its `Enum` associated types.
+///
+/// This allows editors and generic hosts to display the inputs and outputs of a module accessed
+/// through a [`RawModule`] trait object, without compile-time knowledge of its concrete type.
+///
+/// # Example
+///
+/// ```rust
+/// use clogbox_core::module::{ModuleInfo, RawModule};
+/// use clogbox_core::module::stereo::MidSideEncode;
+///
+/// let module: Box<dyn RawModule<Sample = f32>> = Box::new(MidSideEncode::<f32>::default());
+/// let info = module.info();
+/// assert_eq!("f32", info.sample_type);
+/// assert_eq!(["Left", "Right"], info.input_names().collect::<Vec<_>>().as_slice());
+/// assert_eq!(["Mid", "Side"], info.output_names().collect::<Vec<_>>().as_slice());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ModuleInfo {
+    /// Name of the sample type processed by the module, as given by [`std::any::type_name`].
+    pub sample_type: &'static str,
+    /// Input ports of the module, in order.
+    pub inputs: Box<[PortInfo]>,
+    /// Output ports of the module, in order.
+    pub outputs: Box<[PortInfo]>,
+}
+
+impl ModuleInfo {
+    /// Gathers the port information of the given module type.
+    pub fn of<M: Module>() -> Self {
+        Self {
+            sample_type: std::any::type_name::<M::Sample>(),
+            inputs: Self::ports::<M::Inputs>(),
+            outputs: Self::ports::<M::Outputs>(),
+        }
+    }
+
+    /// Returns the names of the input ports, in order.
+    pub fn input_names(&self) -> impl '_ + Iterator<Item = &str> {
+        self.inputs.iter().map(|port| port.name.as_str())
+    }
+
+    /// Returns the names of the output ports, in order.
+    pub fn output_names(&self) -> impl '_ + Iterator<Item = &str> {
+        self.outputs.iter().map(|port| port.name.as_str())
+    }
+
+    fn numbered_ports(count: usize) -> Box<[PortInfo]> {
+        (0..count)
+            .map(|index| PortInfo {
+                index,
+                name: format!("{}", index + 1),
+            })
+            .collect()
+    }
+
+    fn ports<E: Enum>() -> Box<[PortInfo]> {
+        enum_iter::<E>()
+            .map(|port| PortInfo {
+                index: port.cast(),
+                name: port.name().into_owned(),
+            })
+            .collect()
+    }
+}
+
 /// Represents the status of a process.
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ProcessStatus {
@@ -321,6 +523,10 @@ impl<M: Module> RawModule for M {
         <M::Outputs as Enum>::Count::USIZE
     }
 
+    fn info(&self) -> ModuleInfo {
+        ModuleInfo::of::<M>()
+    }
+
     #[inline]
     fn supports_stream(&self, data: StreamData) -> bool {
         M::supports_stream(self, data)
@@ -366,7 +572,7 @@ pub trait ModuleConstructor {
     fn allocate(&self, stream_data: StreamData) -> Self::Module;
 }
 
-impl<'a, M: ModuleConstructor> ModuleConstructor for &'a M {
+impl<M: ModuleConstructor> ModuleConstructor for &M {
     type Module = M::Module;
 
     #[inline]
@@ -388,3 +594,70 @@ impl<M: Module + Clone> ModuleConstructor for ModuleCloner<M> {
         self.module.clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::module::stereo::MidSideEncode;
+
+    /// Raw module implemented directly, without going through [`Module`].
+    struct RawPassthrough;
+
+    impl RawModule for RawPassthrough {
+        type Sample = f64;
+
+        fn inputs(&self) -> usize {
+            2
+        }
+
+        fn outputs(&self) -> usize {
+            1
+        }
+
+        fn supports_stream(&self, _: StreamData) -> bool {
+            true
+        }
+
+        fn process(
+            &mut self,
+            _: &StreamData,
+            inputs: &[&[Self::Sample]],
+            outputs: &mut [&mut [Self::Sample]],
+        ) -> ProcessStatus {
+            outputs[0].copy_from_slice(inputs[0]);
+            ProcessStatus::Running
+        }
+    }
+
+    #[test]
+    fn test_raw_module_default_info() {
+        let info = RawPassthrough.info();
+        assert_eq!("f64", info.sample_type);
+        assert_eq!(
+            ["1", "2"],
+            info.input_names().collect::<Vec<_>>().as_slice()
+        );
+        assert_eq!(["1"], info.output_names().collect::<Vec<_>>().as_slice());
+        let indices = info
+            .inputs
+            .iter()
+            .map(|port| port.index)
+            .collect::<Vec<_>>();
+        assert_eq!([0, 1], indices.as_slice());
+    }
+
+    #[test]
+    fn test_module_info_uses_enum_names() {
+        let module = MidSideEncode::<f32>::default();
+        assert_eq!(
+            ModuleInfo::of::<MidSideEncode<f32>>(),
+            RawModule::info(&module)
+        );
+        let outputs = module
+            .info()
+            .output_names()
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        assert_eq!(["Mid", "Side"], outputs.as_slice());
+    }
+}
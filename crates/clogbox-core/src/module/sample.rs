@@ -40,6 +40,8 @@
 //! let stream_data = &StreamData {
 //!     bpm: 120.,
 //!     block_size: 1,
+//!     transport: Default::default(),
+//!     is_offline: false,
 //!     sample_rate: 44100.,
 //! };
 //! let inputs = EnumMapArray::new(|_| 42.0);
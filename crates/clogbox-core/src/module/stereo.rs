@@ -0,0 +1,846 @@
+//! This module provides adapters to run mono modules on stereo signals, and stereo utilities.
+//!
+//! [`AsStereo`] runs two instances of a module side by side, one per channel, while [`AsMidSide`]
+//! encodes the stereo input into mid and side channels before running the two instances, and
+//! decodes their outputs back to left and right channels afterwards. In both cases, parameters of
+//! the two instances can optionally be linked together.
+//!
+//! For stereo-field processing chains, the [`MidSideEncode`] and [`MidSideDecode`] modules expose
+//! the mid/side conversion as standalone modules, and [`StereoWidth`] scales the width of a stereo
+//! signal.
+//!
+//! # Example
+//!
+//! ```rust
+//! use typenum::U1;
+//! use clogbox_core::module::{Module, ProcessStatus, StreamData};
+//! use clogbox_core::module::stereo::AsStereo;
+//! use clogbox_core::r#enum::Sequential;
+//! use clogbox_core::r#enum::enum_map::EnumMapArray;
+//!
+//! #[derive(Debug, Clone)]
+//! struct Halve;
+//!
+//! impl Module for Halve {
+//!     type Sample = f32;
+//!     type Inputs = Sequential<U1>;
+//!     type Outputs = Sequential<U1>;
+//!
+//!     fn supports_stream(&self, _: StreamData) -> bool {
+//!         true
+//!     }
+//!
+//!     fn latency(&self, input_latencies: EnumMapArray<Self::Inputs, f64>) -> EnumMapArray<Self::Outputs, f64> {
+//!         input_latencies
+//!     }
+//!
+//!     fn process(&mut self, _: &StreamData, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) -> ProcessStatus {
+//!         for (o, i) in outputs[0].iter_mut().zip(inputs[0]) {
+//!             *o = 0.5 * i;
+//!         }
+//!         ProcessStatus::Running
+//!     }
+//! }
+//!
+//! let mut stereo = AsStereo::from_mono(Halve);
+//! let stream_data = StreamData::new(44100., 120., 4);
+//! let (left, right) = ([1., 2., 3., 4.], [-1., -2., -3., -4.]);
+//! let (mut out_left, mut out_right) = ([0.; 4], [0.; 4]);
+//! stereo.process(&stream_data, &[&left, &right], &mut [&mut out_left, &mut out_right]);
+//! assert_eq!([0.5, 1., 1.5, 2.], out_left);
+//! assert_eq!([-0.5, -1., -1.5, -2.], out_right);
+//! ```
+use crate::module::{Module, ProcessStatus, StreamData};
+use crate::param::smoothed::Smoothed;
+use crate::param::value::Value;
+use crate::param::{GetParameter, SetParameter};
+use crate::r#enum::enum_map::EnumMapArray;
+use crate::r#enum::{CartesianProduct, Enum, Sequential};
+use az::{Cast, CastFrom};
+use num_traits::{Num, Zero};
+use numeric_array::ArrayLength;
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::ops;
+use typenum::{Unsigned, U1, U2};
+
+/// Channels of a stereo signal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stereo {
+    /// Left channel
+    Left,
+    /// Right channel
+    Right,
+}
+
+impl Cast<usize> for Stereo {
+    fn cast(self) -> usize {
+        match self {
+            Self::Left => 0,
+            Self::Right => 1,
+        }
+    }
+}
+
+impl CastFrom<usize> for Stereo {
+    fn cast_from(src: usize) -> Self {
+        match src {
+            0 => Self::Left,
+            1 => Self::Right,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Enum for Stereo {
+    type Count = U2;
+
+    fn name(&self) -> Cow<'_, str> {
+        match self {
+            Self::Left => Cow::from("Left"),
+            Self::Right => Cow::from("Right"),
+        }
+    }
+}
+
+/// Encodes a left/right sample pair into a mid/side sample pair.
+///
+/// This is the inverse of [`decode_mid_side`].
+///
+/// # Example
+///
+/// ```rust
+/// use clogbox_core::module::stereo::encode_mid_side;
+/// assert_eq!((0.5, 0.5), encode_mid_side(1.0, 0.0));
+/// ```
+#[inline]
+pub fn encode_mid_side<T: Copy + Num + CastFrom<f64>>(left: T, right: T) -> (T, T) {
+    let half = T::cast_from(0.5);
+    (half * (left + right), half * (left - right))
+}
+
+/// Decodes a mid/side sample pair into a left/right sample pair.
+///
+/// This is the inverse of [`encode_mid_side`].
+///
+/// # Example
+///
+/// ```rust
+/// use clogbox_core::module::stereo::decode_mid_side;
+/// assert_eq!((1.0, 0.0), decode_mid_side(0.5, 0.5));
+/// ```
+#[inline]
+pub fn decode_mid_side<T: Copy + Num>(mid: T, side: T) -> (T, T) {
+    (mid + side, mid - side)
+}
+
+/// Runs two instances of a module, one for each channel of a stereo signal.
+///
+/// The inputs and outputs of the adapter are the inputs and outputs of the inner module, for each
+/// stereo channel, that is, all left ports, followed by all right ports.
+///
+/// When the parameters are linked, setting a parameter of either channel sets it on both
+/// instances.
+#[derive(Debug, Clone)]
+pub struct AsStereo<M> {
+    /// Module instance processing the left channel.
+    pub left: M,
+    /// Module instance processing the right channel.
+    pub right: M,
+    linked: bool,
+}
+
+impl<M> AsStereo<M> {
+    /// Creates a new stereo adapter from the two given instances. Parameters are unlinked by
+    /// default.
+    pub fn new(left: M, right: M) -> Self {
+        Self {
+            left,
+            right,
+            linked: false,
+        }
+    }
+
+    /// Creates a new stereo adapter by duplicating the given module. Parameters are unlinked by
+    /// default.
+    pub fn from_mono(module: M) -> Self
+    where
+        M: Clone,
+    {
+        Self::new(module.clone(), module)
+    }
+
+    /// Returns whether the parameters of both instances are linked.
+    pub fn linked(&self) -> bool {
+        self.linked
+    }
+
+    /// Sets whether the parameters of both instances are linked. Linking does not synchronize
+    /// parameter values until they are next set.
+    pub fn set_linked(&mut self, linked: bool) {
+        self.linked = linked;
+    }
+
+    /// Sets whether the parameters of both instances are linked, returning the modified adapter.
+    pub fn with_linked(mut self, linked: bool) -> Self {
+        self.set_linked(linked);
+        self
+    }
+
+    /// Returns the module instance processing the given channel.
+    pub fn channel(&self, channel: Stereo) -> &M {
+        match channel {
+            Stereo::Left => &self.left,
+            Stereo::Right => &self.right,
+        }
+    }
+
+    /// Mutably returns the module instance processing the given channel.
+    pub fn channel_mut(&mut self, channel: Stereo) -> &mut M {
+        match channel {
+            Stereo::Left => &mut self.left,
+            Stereo::Right => &mut self.right,
+        }
+    }
+}
+
+impl<M: Module> Module for AsStereo<M>
+where
+    U2: ops::Mul<<M::Inputs as Enum>::Count, Output: Unsigned + ArrayLength>,
+    U2: ops::Mul<<M::Outputs as Enum>::Count, Output: Unsigned + ArrayLength>,
+{
+    type Sample = M::Sample;
+    type Inputs = CartesianProduct<Stereo, M::Inputs>;
+    type Outputs = CartesianProduct<Stereo, M::Outputs>;
+
+    fn supports_stream(&self, data: StreamData) -> bool {
+        self.left.supports_stream(data) && self.right.supports_stream(data)
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.left.reallocate(stream_data);
+        self.right.reallocate(stream_data);
+    }
+
+    fn reset(&mut self) {
+        self.left.reset();
+        self.right.reset();
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let left = self.left.latency(EnumMapArray::new(|i| {
+            input_latencies[CartesianProduct(Stereo::Left, i)]
+        }));
+        let right = self.right.latency(EnumMapArray::new(|i| {
+            input_latencies[CartesianProduct(Stereo::Right, i)]
+        }));
+        EnumMapArray::new(|CartesianProduct(channel, o)| match channel {
+            Stereo::Left => left[o],
+            Stereo::Right => right[o],
+        })
+    }
+
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let (in_left, in_right) = inputs.split_at(<M::Inputs as Enum>::Count::USIZE);
+        let (out_left, out_right) = outputs.split_at_mut(<M::Outputs as Enum>::Count::USIZE);
+        let left_status = self.left.process(stream_data, in_left, out_left);
+        let right_status = self.right.process(stream_data, in_right, out_right);
+        left_status.merge(&right_status)
+    }
+}
+
+impl<M: GetParameter> GetParameter for AsStereo<M>
+where
+    U2: ops::Mul<<M::Param as Enum>::Count, Output: Unsigned + ArrayLength>,
+{
+    type Param = CartesianProduct<Stereo, M::Param>;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        let CartesianProduct(channel, param) = param;
+        self.channel(channel).get_param_raw(param)
+    }
+}
+
+impl<M: SetParameter> SetParameter for AsStereo<M>
+where
+    U2: ops::Mul<<M::Param as Enum>::Count, Output: Unsigned + ArrayLength>,
+{
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        let CartesianProduct(channel, param) = param;
+        if self.linked {
+            self.left.set_param_raw(param, value);
+            self.right.set_param_raw(param, value);
+        } else {
+            self.channel_mut(channel).set_param_raw(param, value);
+        }
+    }
+}
+
+/// Runs two instances of a module on the mid and side channels of a stereo signal.
+///
+/// The stereo input is encoded into mid and side channels, which are respectively processed by
+/// the [`Stereo::Left`] and [`Stereo::Right`] instances of the inner [`AsStereo`] adapter. Their
+/// outputs are then decoded back into left and right channels.
+///
+/// The port layout is the same as [`AsStereo`].
+#[derive(Debug, Clone)]
+pub struct AsMidSide<M: Module> {
+    inner: AsStereo<M>,
+    encoded: Box<[Box<[M::Sample]>]>,
+}
+
+impl<M: Module> AsMidSide<M> {
+    /// Creates a new mid/side adapter from an instance processing the mid channel, and one
+    /// processing the side channel. Parameters are unlinked by default.
+    pub fn new(mid: M, side: M) -> Self {
+        Self {
+            inner: AsStereo::new(mid, side),
+            encoded: Box::new([]),
+        }
+    }
+
+    /// Creates a new mid/side adapter by duplicating the given module. Parameters are unlinked by
+    /// default.
+    pub fn from_mono(module: M) -> Self
+    where
+        M: Clone,
+    {
+        Self::new(module.clone(), module)
+    }
+
+    /// Returns the inner stereo adapter, where the left instance processes the mid channel, and
+    /// the right instance processes the side channel.
+    pub fn inner(&self) -> &AsStereo<M> {
+        &self.inner
+    }
+
+    /// Mutably returns the inner stereo adapter, where the left instance processes the mid
+    /// channel, and the right instance processes the side channel.
+    pub fn inner_mut(&mut self) -> &mut AsStereo<M> {
+        &mut self.inner
+    }
+
+    /// Sets whether the parameters of both instances are linked, returning the modified adapter.
+    pub fn with_linked(mut self, linked: bool) -> Self {
+        self.inner.set_linked(linked);
+        self
+    }
+}
+
+impl<M: Module> Module for AsMidSide<M>
+where
+    M::Sample: Send + Copy + Num + CastFrom<f64>,
+    U2: ops::Mul<<M::Inputs as Enum>::Count, Output: Unsigned + ArrayLength>,
+    U2: ops::Mul<<M::Outputs as Enum>::Count, Output: Unsigned + ArrayLength>,
+{
+    type Sample = M::Sample;
+    type Inputs = CartesianProduct<Stereo, M::Inputs>;
+    type Outputs = CartesianProduct<Stereo, M::Outputs>;
+
+    fn supports_stream(&self, data: StreamData) -> bool {
+        // The encoding buffers are only allocated in `reallocate`
+        self.encoded.len() == <Self::Inputs as Enum>::Count::USIZE
+            && self.encoded.iter().all(|buf| data.block_size <= buf.len())
+            && self.inner.supports_stream(data)
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.encoded = (0..<Self::Inputs as Enum>::Count::USIZE)
+            .map(|_| {
+                std::iter::repeat_with(M::Sample::zero)
+                    .take(stream_data.block_size)
+                    .collect()
+            })
+            .collect();
+        self.inner.reallocate(stream_data);
+    }
+
+    fn reset(&mut self) {
+        for buf in &mut self.encoded {
+            buf.fill_with(M::Sample::zero);
+        }
+        self.inner.reset();
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        // Both mid and side depend on both input channels, and both output channels depend on
+        // both mid and side outputs.
+        let encoded = EnumMapArray::new(|CartesianProduct(_, i)| {
+            input_latencies[CartesianProduct(Stereo::Left, i)]
+                .max(input_latencies[CartesianProduct(Stereo::Right, i)])
+        });
+        let decoded = self.inner.latency(encoded);
+        EnumMapArray::new(|CartesianProduct(_, o)| {
+            decoded[CartesianProduct(Stereo::Left, o)]
+                .max(decoded[CartesianProduct(Stereo::Right, o)])
+        })
+    }
+
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let block_size = stream_data.block_size;
+        let num_inputs = <M::Inputs as Enum>::Count::USIZE;
+        let num_outputs = <M::Outputs as Enum>::Count::USIZE;
+
+        let (in_left, in_right) = inputs.split_at(num_inputs);
+        let (mid, side) = self.encoded.split_at_mut(num_inputs);
+        for (((l, r), m), s) in in_left.iter().zip(in_right).zip(mid).zip(side) {
+            for i in 0..block_size {
+                (m[i], s[i]) = encode_mid_side(l[i], r[i]);
+            }
+        }
+
+        let encoded =
+            EnumMapArray::<Self::Inputs, _>::new(|k| &self.encoded[k.cast()][..block_size]);
+        let status = self.inner.process(stream_data, encoded.as_slice(), outputs);
+
+        let (out_left, out_right) = outputs.split_at_mut(num_outputs);
+        for (l, r) in out_left.iter_mut().zip(out_right) {
+            for i in 0..block_size {
+                (l[i], r[i]) = decode_mid_side(l[i], r[i]);
+            }
+        }
+        status
+    }
+}
+
+impl<M: Module + GetParameter> GetParameter for AsMidSide<M>
+where
+    U2: ops::Mul<<M::Param as Enum>::Count, Output: Unsigned + ArrayLength>,
+{
+    type Param = CartesianProduct<Stereo, M::Param>;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        self.inner.get_param_raw(param)
+    }
+}
+
+impl<M: Module + SetParameter> SetParameter for AsMidSide<M>
+where
+    U2: ops::Mul<<M::Param as Enum>::Count, Output: Unsigned + ArrayLength>,
+{
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        self.inner.set_param_raw(param, value)
+    }
+}
+
+/// Channels of a mid/side encoded stereo signal.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MidSide {
+    /// Mid channel, the correlated part of the stereo signal.
+    Mid,
+    /// Side channel, the difference between the left and right channels.
+    Side,
+}
+
+impl Cast<usize> for MidSide {
+    fn cast(self) -> usize {
+        match self {
+            Self::Mid => 0,
+            Self::Side => 1,
+        }
+    }
+}
+
+impl CastFrom<usize> for MidSide {
+    fn cast_from(src: usize) -> Self {
+        match src {
+            0 => Self::Mid,
+            1 => Self::Side,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Enum for MidSide {
+    type Count = U2;
+
+    fn name(&self) -> Cow<'_, str> {
+        match self {
+            Self::Mid => Cow::from("Mid"),
+            Self::Side => Cow::from("Side"),
+        }
+    }
+}
+
+/// A module encoding a left/right stereo signal into mid and side channels.
+///
+/// This is the inverse of [`MidSideDecode`].
+#[derive(Debug, Clone)]
+pub struct MidSideEncode<T>(PhantomData<fn(T) -> T>);
+
+impl<T> Default for MidSideEncode<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: 'static + Send + Copy + Num + CastFrom<f64>> Module for MidSideEncode<T> {
+    type Sample = T;
+    type Inputs = Stereo;
+    type Outputs = MidSide;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let latency = input_latencies[Stereo::Left].max(input_latencies[Stereo::Right]);
+        EnumMapArray::new(|_| latency)
+    }
+
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let (mid, side) = outputs.split_at_mut(1);
+        let samples = mid[0].iter_mut().zip(side[0].iter_mut());
+        for ((mid, side), (&left, &right)) in samples.zip(inputs[0].iter().zip(inputs[1])) {
+            (*mid, *side) = encode_mid_side(left, right);
+        }
+        ProcessStatus::Running
+    }
+}
+
+/// A module decoding mid and side channels into a left/right stereo signal.
+///
+/// This is the inverse of [`MidSideEncode`].
+#[derive(Debug, Clone)]
+pub struct MidSideDecode<T>(PhantomData<fn(T) -> T>);
+
+impl<T> Default for MidSideDecode<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: 'static + Send + Copy + Num> Module for MidSideDecode<T> {
+    type Sample = T;
+    type Inputs = MidSide;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let latency = input_latencies[MidSide::Mid].max(input_latencies[MidSide::Side]);
+        EnumMapArray::new(|_| latency)
+    }
+
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let (left, right) = outputs.split_at_mut(1);
+        let samples = left[0].iter_mut().zip(right[0].iter_mut());
+        for ((left, right), (&mid, &side)) in samples.zip(inputs[0].iter().zip(inputs[1])) {
+            (*left, *right) = decode_mid_side(mid, side);
+        }
+        ProcessStatus::Running
+    }
+}
+
+/// A module scaling the width of a stereo signal, by scaling its side channel.
+///
+/// A width of 0 collapses the signal to mono, a width of 1 leaves it unchanged, and widths above 1
+/// exaggerate the stereo image. The width, which is smoothed, is its only parameter.
+#[derive(Debug, Clone)]
+pub struct StereoWidth<T> {
+    width: Smoothed<T>,
+}
+
+impl<T: Copy + Num + CastFrom<f64>> StereoWidth<T> {
+    /// Maximum width that can be set.
+    pub const MAX_WIDTH: f32 = 2.;
+    /// Time taken by width changes to be fully applied, in seconds.
+    const SMOOTHING_TIME: f32 = 0.01;
+
+    /// Creates a new stereo width module, with the given initial width.
+    pub fn new(sample_rate: f64, width: f32) -> Self {
+        Self {
+            width: Smoothed::new(
+                sample_rate as _,
+                Self::SMOOTHING_TIME,
+                T::cast_from(width.clamp(0., Self::MAX_WIDTH) as f64),
+            ),
+        }
+    }
+
+    /// Sets the target width, clamped to 0..[`Self::MAX_WIDTH`].
+    pub fn set_width(&mut self, width: f32) {
+        self.width
+            .set_target(T::cast_from(width.clamp(0., Self::MAX_WIDTH) as f64));
+    }
+}
+
+impl<T: 'static + Send + Copy + Num + CastFrom<f64>> Module for StereoWidth<T> {
+    type Sample = T;
+    type Inputs = Stereo;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.width.set_sample_rate(stream_data.sample_rate as _);
+    }
+
+    fn reset(&mut self) {
+        self.width.reset(self.width.target());
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let latency = input_latencies[Stereo::Left].max(input_latencies[Stereo::Right]);
+        EnumMapArray::new(|_| latency)
+    }
+
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let (out_left, out_right) = outputs.split_at_mut(1);
+        let samples = out_left[0].iter_mut().zip(out_right[0].iter_mut());
+        for ((out_left, out_right), (&left, &right)) in samples.zip(inputs[0].iter().zip(inputs[1]))
+        {
+            let width = self.width.next_value();
+            let (mid, side) = encode_mid_side(left, right);
+            (*out_left, *out_right) = decode_mid_side(mid, width * side);
+        }
+        ProcessStatus::Running
+    }
+}
+
+impl<T: Copy + Cast<f32>> GetParameter for StereoWidth<T> {
+    type Param = Sequential<U1>;
+
+    fn get_param_raw(&self, _: Self::Param) -> Value<'_> {
+        Value::Float(self.width.target().cast())
+    }
+}
+
+impl<T: Copy + Num + Cast<f32> + CastFrom<f64>> SetParameter for StereoWidth<T> {
+    fn set_param_raw(&mut self, _: Self::Param, value: Value) {
+        if let Ok(width) = f32::try_from(value) {
+            self.set_width(width);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#enum::seq;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+    use typenum::U3;
+
+    /// Test module multiplying its input by a gain parameter, and outputting the input, the
+    /// scaled input and the negated input.
+    #[derive(Debug, Clone)]
+    struct TestGain(f32);
+
+    impl Module for TestGain {
+        type Sample = f32;
+        type Inputs = Sequential<U1>;
+        type Outputs = Sequential<U3>;
+
+        fn supports_stream(&self, _: StreamData) -> bool {
+            true
+        }
+
+        fn latency(
+            &self,
+            input_latencies: EnumMapArray<Self::Inputs, f64>,
+        ) -> EnumMapArray<Self::Outputs, f64> {
+            EnumMapArray::new(|o: Self::Outputs| input_latencies[seq(0)] + o.cast() as f64)
+        }
+
+        fn process(
+            &mut self,
+            _: &StreamData,
+            inputs: &[&[f32]],
+            outputs: &mut [&mut [f32]],
+        ) -> ProcessStatus {
+            for (i, x) in inputs[0].iter().enumerate() {
+                outputs[0][i] = *x;
+                outputs[1][i] = self.0 * x;
+                outputs[2][i] = -x;
+            }
+            ProcessStatus::Running
+        }
+    }
+
+    impl GetParameter for TestGain {
+        type Param = Sequential<U1>;
+
+        fn get_param_raw(&self, _: Self::Param) -> Value<'_> {
+            Value::Float(self.0)
+        }
+    }
+
+    impl SetParameter for TestGain {
+        fn set_param_raw(&mut self, _: Self::Param, value: Value) {
+            self.0 = value.try_into().unwrap();
+        }
+    }
+
+    const STREAM_DATA: StreamData = StreamData::new(44100., 120., 3);
+
+    fn process<M: Module<Sample = f32>>(
+        module: &mut M,
+        left: [f32; 3],
+        right: [f32; 3],
+    ) -> Vec<[f32; 3]> {
+        let mut outputs = vec![[0.; 3]; 6];
+        let mut output_slices = Vec::from_iter(outputs.iter_mut().map(|o| &mut o[..]));
+        module.process(&STREAM_DATA, &[&left, &right], &mut output_slices);
+        outputs
+    }
+
+    #[rstest]
+    fn test_as_stereo_port_layout() {
+        let mut module = AsStereo::new(TestGain(2.), TestGain(3.));
+        let outputs = process(&mut module, [1., 2., 3.], [4., 5., 6.]);
+        assert_eq!(
+            vec![
+                [1., 2., 3.],
+                [2., 4., 6.],
+                [-1., -2., -3.],
+                [4., 5., 6.],
+                [12., 15., 18.],
+                [-4., -5., -6.],
+            ],
+            outputs
+        );
+    }
+
+    #[rstest]
+    fn test_as_stereo_latency() {
+        let module = AsStereo::from_mono(TestGain(1.));
+        let latency = module.latency(EnumMapArray::new(|CartesianProduct(ch, _)| match ch {
+            Stereo::Left => 0.,
+            Stereo::Right => 10.,
+        }));
+        assert_eq!(&[0., 1., 2., 10., 11., 12.], latency.as_slice());
+    }
+
+    #[rstest]
+    #[case(false, 3.)]
+    #[case(true, 2.)]
+    fn test_as_stereo_linked_params(#[case] linked: bool, #[case] expected_right: f32) {
+        let mut module = AsStereo::new(TestGain(1.), TestGain(3.)).with_linked(linked);
+        module.set_param(CartesianProduct(Stereo::Left, seq(0)), 2.0f32);
+        assert_eq!(2., module.left.0);
+        assert_eq!(expected_right, module.right.0);
+    }
+
+    #[rstest]
+    fn test_as_mid_side_identity() {
+        let mut module = AsMidSide::from_mono(TestGain(1.));
+        module.reallocate(STREAM_DATA);
+        let outputs = process(&mut module, [1., 2., 3.], [4., 5., 6.]);
+        assert_eq!([1., 2., 3.], outputs[1]);
+        assert_eq!([4., 5., 6.], outputs[4]);
+    }
+
+    #[rstest]
+    fn test_as_mid_side_requires_reallocation() {
+        let mut module = AsMidSide::from_mono(TestGain(1.));
+        assert!(!module.supports_stream(STREAM_DATA));
+        module.reallocate(STREAM_DATA);
+        assert!(module.supports_stream(STREAM_DATA));
+    }
+
+    #[rstest]
+    fn test_as_mid_side_process_side_only() {
+        // Zeroing the mid channel removes the correlated part of the signal
+        let mut module = AsMidSide::new(TestGain(0.), TestGain(1.));
+        module.reallocate(STREAM_DATA);
+        let outputs = process(&mut module, [1., 1., 2.], [1., -1., 0.]);
+        assert_eq!([0., 1., 1.], outputs[1]);
+        assert_eq!([0., -1., -1.], outputs[4]);
+    }
+
+    fn process_stereo<T: Copy + Zero, M: Module<Sample = T>>(
+        module: &mut M,
+        left: [T; 3],
+        right: [T; 3],
+    ) -> [[T; 3]; 2] {
+        let mut outputs = [[T::zero(); 3]; 2];
+        let [out_left, out_right] = &mut outputs;
+        module.process(&STREAM_DATA, &[&left, &right], &mut [out_left, out_right]);
+        outputs
+    }
+
+    #[rstest]
+    fn test_mid_side_roundtrip() {
+        let (left, right) = ([1., 0., 0.5], [0., 1., 0.5]);
+        let encoded = process_stereo(&mut MidSideEncode::default(), left, right);
+        assert_eq!([[0.5, 0.5, 0.5], [0.5, -0.5, 0.]], encoded);
+        let decoded = process_stereo(&mut MidSideDecode::default(), encoded[0], encoded[1]);
+        assert_eq!([left, right], decoded);
+    }
+
+    #[rstest]
+    #[case(0., [[0.5, 0.5, 0.5], [0.5, 0.5, 0.5]])]
+    #[case(1., [[1., 0., 0.5], [0., 1., 0.5]])]
+    #[case(2., [[1.5, -0.5, 0.5], [-0.5, 1.5, 0.5]])]
+    fn test_stereo_width(#[case] width: f32, #[case] expected: [[f32; 3]; 2]) {
+        let mut module = StereoWidth::new(STREAM_DATA.sample_rate, width);
+        let outputs = process_stereo(&mut module, [1., 0., 0.5], [0., 1., 0.5]);
+        assert_eq!(expected, outputs);
+    }
+
+    #[rstest]
+    fn test_stereo_width_f64() {
+        let mut module = StereoWidth::<f64>::new(STREAM_DATA.sample_rate, 2.);
+        let outputs = process_stereo(&mut module, [1., 0., 0.5], [0., 1., 0.5]);
+        assert_eq!([[1.5, -0.5, 0.5], [-0.5, 1.5, 0.5]], outputs);
+    }
+
+    #[rstest]
+    fn test_stereo_width_f64_precision() {
+        let (from, to) = (0.3f32, 0.7f32);
+        let mut module = StereoWidth::<f64>::new(STREAM_DATA.sample_rate, from);
+        module.set_width(to);
+        // Widths smoothed in f32 would be off by about 1e-8
+        let [left, right] = process_stereo(&mut module, [1.; 3], [0.; 3]);
+        for (i, (left, right)) in left.into_iter().zip(right).enumerate() {
+            let width = from as f64 + (to as f64 - from as f64) * (i + 1) as f64 / 441.;
+            assert_relative_eq!(0.5 + 0.5 * width, left, epsilon = 1e-15);
+            assert_relative_eq!(0.5 - 0.5 * width, right, epsilon = 1e-15);
+        }
+    }
+}
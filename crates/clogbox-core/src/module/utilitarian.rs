@@ -1,17 +1,23 @@
 //! This module provides the core functionalities and structures for handling various
 //! audio processing components. It includes definitions for processing statuses,
 //! stream metadata, and configuration, as well as implementations of different
-//! processing units.
+//! processing units, such as gains, panners, mixers and denormal flushing, as well as
+//! combinators composing modules in series or in parallel.
+use crate::math::dsp::flush_denormal;
+use crate::module::stereo::Stereo;
 use crate::module::{Module, ProcessStatus, StreamData};
 use crate::param::curve::ParamCurve;
+use crate::param::smoothed::Smoothed;
+use crate::param::value::Value;
+use crate::param::{GetParameter, SetParameter};
 use crate::r#enum::enum_map::{EnumMap, EnumMapArray, EnumMapBox};
-use crate::r#enum::{enum_iter, CartesianProduct, Enum};
-use az::CastFrom;
-use num_traits::{Num, NumAssign, Zero};
+use crate::r#enum::{enum_iter, seq, CartesianProduct, Enum, Sequential};
+use az::{Cast, CastFrom};
+use num_traits::{Float, FloatConst, Num, NumAssign, Zero};
 use numeric_array::ArrayLength;
 use std::marker::PhantomData;
 use std::ops;
-use typenum::Unsigned;
+use typenum::{Unsigned, U1};
 
 /// A matrix that sums the inputs given a matrix of input:output coefficients.
 ///
@@ -73,7 +79,7 @@ where
 }
 
 impl<
-        T: 'static + Copy + Send + NumAssign + Num + Zero + CastFrom<f32>,
+        T: 'static + Copy + Send + NumAssign + Num + Zero + CastFrom<f64>,
         In: 'static + Enum,
         Out: 'static + Enum,
     > Module for SummingMatrix<T, In, Out>
@@ -129,7 +135,7 @@ where
             let parr = &self.params[param];
             // TODO: simd
             for i in 0..block_size {
-                let k = T::cast_from(parr.get_value_sample(i));
+                let k = T::cast_from(parr.get_value_sample(i) as f64);
                 out_buf[i] += k * in_buf[i];
             }
         }
@@ -214,16 +220,618 @@ where
     }
 }
 
+/// Allocates a silent buffer of `block_size` samples for each variant of `E`.
+fn allocate_buffers<E: Enum, T: Zero>(block_size: usize) -> EnumMapArray<E, Box<[T]>> {
+    EnumMapArray::new(|_| std::iter::repeat_with(T::zero).take(block_size).collect())
+}
+
+/// A struct for running two modules in series, statically connecting each output of the first
+/// module to the matching input of the second module.
+///
+/// Unlike [`Series`], no switch function is needed, which makes it the simplest way to compose
+/// fixed pipelines at compile time.
+///
+/// # Example
+///
+/// ```rust
+/// use clogbox_core::module::{Module, StreamData};
+/// use clogbox_core::module::utilitarian::{Chain, Gain};
+/// use clogbox_core::r#enum::Sequential;
+/// use typenum::U1;
+///
+/// let stream_data = StreamData::new(44100.0, 120.0, 4);
+/// let first = Gain::<f32, Sequential<U1>>::new(stream_data.sample_rate, 0.5);
+/// let second = Gain::<f32, Sequential<U1>>::new(stream_data.sample_rate, 0.5);
+/// let mut chain = Chain::new(first, second);
+/// chain.reallocate(stream_data);
+///
+/// let mut output = [0.0; 4];
+/// chain.process(&stream_data, &[&[1.0; 4]], &mut [&mut output]);
+/// assert_eq!([0.25; 4], output);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Chain<A: Module, B> {
+    /// The first audio module of the chain.
+    pub first: A,
+    /// The second audio module of the chain, fed with the outputs of the first.
+    pub second: B,
+    inner_buffer: EnumMapArray<A::Outputs, Box<[A::Sample]>>,
+}
+
+impl<A: Module, B> Chain<A, B>
+where
+    A::Sample: Zero,
+{
+    /// Creates a new chain running `first` and then `second`.
+    ///
+    /// The intermediate buffers are allocated when the chain is reallocated.
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+            inner_buffer: allocate_buffers(0),
+        }
+    }
+}
+
+impl<A: Module, B: Module<Sample = A::Sample, Inputs = A::Outputs>> Module for Chain<A, B>
+where
+    A::Sample: Send + Zero,
+{
+    type Sample = A::Sample;
+    type Inputs = A::Inputs;
+    type Outputs = B::Outputs;
+
+    fn supports_stream(&self, data: StreamData) -> bool {
+        self.inner_buffer
+            .iter()
+            .all(|(_, arr)| data.block_size <= arr.len())
+            && self.first.supports_stream(data)
+            && self.second.supports_stream(data)
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.inner_buffer = allocate_buffers(stream_data.block_size);
+        self.first.reallocate(stream_data);
+        self.second.reallocate(stream_data);
+    }
+
+    fn reset(&mut self) {
+        for x in self.inner_buffer.values_mut() {
+            x.fill_with(A::Sample::zero);
+        }
+        self.first.reset();
+        self.second.reset();
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        self.second.latency(self.first.latency(input_latencies))
+    }
+
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let block_size = stream_data.block_size;
+        let first_status = {
+            let mut intermediate = EnumMapArray::<A::Outputs, _>::from_iter(
+                self.inner_buffer
+                    .values_mut()
+                    .map(|buf| &mut buf[..block_size]),
+            );
+            self.first
+                .process(stream_data, inputs, intermediate.as_slice_mut())
+        };
+        let intermediate = EnumMapArray::new(|k| &self.inner_buffer[k][..block_size]);
+        let second_status = self
+            .second
+            .process(stream_data, intermediate.as_slice(), outputs);
+        first_status.merge(&second_status)
+    }
+}
+
+/// Delay line aligning the output of the lowest latency branch of a [`ParallelSum`] with the
+/// other branch.
+#[derive(Debug, Clone)]
+struct DelayCompensation<T> {
+    /// Whether the outputs of the first module are delayed, rather than those of the second.
+    delay_first: bool,
+    buffer: Box<[T]>,
+    position: usize,
+}
+
+impl<T: Copy + Zero> DelayCompensation<T> {
+    /// Creates a delay line compensating a latency difference of `first - second` samples,
+    /// rounded to the nearest sample.
+    fn new(first: f64, second: f64) -> Self {
+        let delay = (first - second).abs().round() as usize;
+        Self {
+            delay_first: first < second,
+            buffer: std::iter::repeat_with(T::zero).take(delay).collect(),
+            position: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill_with(T::zero);
+        self.position = 0;
+    }
+
+    fn process(&mut self, samples: &mut [T]) {
+        if self.buffer.is_empty() {
+            return;
+        }
+        for x in samples {
+            *x = std::mem::replace(&mut self.buffer[self.position], *x);
+            self.position = (self.position + 1) % self.buffer.len();
+        }
+    }
+}
+
+/// A struct for running two modules with the same ports in parallel, summing their outputs.
+///
+/// Both modules receive the same inputs, and each output of the resulting module is the sum of the
+/// matching outputs of the two modules. When the modules have different latencies, the output of
+/// the lowest latency module is delayed so that both are aligned before being summed, and the
+/// resulting latency is the highest of the two.
+///
+/// The latencies of both modules are queried when the module is reallocated, and rounded to the
+/// nearest sample; modules whose latency changes must therefore be reallocated for the
+/// compensation to follow.
+#[derive(Debug, Clone)]
+pub struct ParallelSum<A: Module, B> {
+    /// The first audio module.
+    pub first: A,
+    /// The second audio module.
+    pub second: B,
+    inner_buffer: EnumMapArray<A::Outputs, Box<[A::Sample]>>,
+    compensation: EnumMapArray<A::Outputs, DelayCompensation<A::Sample>>,
+}
+
+impl<A: Module, B> ParallelSum<A, B>
+where
+    A::Sample: Copy + Zero,
+{
+    /// Creates a new module running `first` and `second` in parallel.
+    ///
+    /// The intermediate buffers and latency compensation are allocated when the module is
+    /// reallocated.
+    pub fn new(first: A, second: B) -> Self {
+        Self {
+            first,
+            second,
+            inner_buffer: allocate_buffers(0),
+            compensation: EnumMapArray::new(|_| DelayCompensation::new(0., 0.)),
+        }
+    }
+}
+
+impl<A: Module, B: Module<Sample = A::Sample, Inputs = A::Inputs, Outputs = A::Outputs>> Module
+    for ParallelSum<A, B>
+where
+    A::Sample: Send + Copy + NumAssign,
+{
+    type Sample = A::Sample;
+    type Inputs = A::Inputs;
+    type Outputs = A::Outputs;
+
+    fn supports_stream(&self, data: StreamData) -> bool {
+        self.inner_buffer
+            .iter()
+            .all(|(_, arr)| data.block_size <= arr.len())
+            && self.first.supports_stream(data)
+            && self.second.supports_stream(data)
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.inner_buffer = allocate_buffers(stream_data.block_size);
+        self.first.reallocate(stream_data);
+        self.second.reallocate(stream_data);
+
+        let first = self.first.latency(EnumMapArray::new(|_| 0.));
+        let second = self.second.latency(EnumMapArray::new(|_| 0.));
+        self.compensation = EnumMapArray::new(|k| DelayCompensation::new(first[k], second[k]));
+    }
+
+    fn reset(&mut self) {
+        for x in self.inner_buffer.values_mut() {
+            x.fill_with(A::Sample::zero);
+        }
+        for compensation in self.compensation.values_mut() {
+            compensation.reset();
+        }
+        self.first.reset();
+        self.second.reset();
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        // The lowest latency branch is delayed to match the other one
+        let first = self.first.latency(input_latencies.clone());
+        let second = self.second.latency(input_latencies);
+        EnumMapArray::new(|k| first[k].max(second[k]))
+    }
+
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let block_size = stream_data.block_size;
+        let first_status = self.first.process(stream_data, inputs, outputs);
+        let second_status = {
+            let mut second_outputs = EnumMapArray::<A::Outputs, _>::from_iter(
+                self.inner_buffer
+                    .values_mut()
+                    .map(|buf| &mut buf[..block_size]),
+            );
+            self.second
+                .process(stream_data, inputs, second_outputs.as_slice_mut())
+        };
+        let buffers = self.inner_buffer.values_mut();
+        for ((out, buf), compensation) in outputs
+            .iter_mut()
+            .zip(buffers)
+            .zip(self.compensation.values_mut())
+        {
+            let (out, buf) = (&mut out[..block_size], &mut buf[..block_size]);
+            compensation.process(if compensation.delay_first { out } else { buf });
+            for (o, &x) in out.iter_mut().zip(&*buf) {
+                *o += x;
+            }
+        }
+        first_status.merge(&second_status)
+    }
+}
+
+/// Time taken by the parameter changes of the [`Gain`], [`Pan`] and [`Mixer`] modules to be fully
+/// applied, in seconds.
+const SMOOTHING_TIME: f32 = 0.01;
+
+/// A module applying the same smoothed gain to all of its channels.
+///
+/// The gain is linear, and is its only parameter.
+#[derive(Debug, Clone)]
+pub struct Gain<T, E> {
+    gain: Smoothed<T>,
+    __io: PhantomData<fn(T) -> E>,
+}
+
+impl<T: Copy + Num + CastFrom<f64>, E> Gain<T, E> {
+    /// Creates a new gain module with the given initial (linear) gain.
+    pub fn new(sample_rate: f64, gain: f32) -> Self {
+        Self {
+            gain: Smoothed::new(sample_rate as _, SMOOTHING_TIME, T::cast_from(gain as f64)),
+            __io: PhantomData,
+        }
+    }
+
+    /// Sets the target gain, which is reached after a short ramp.
+    pub fn set_gain(&mut self, gain: f32) {
+        self.gain.set_target(T::cast_from(gain as f64));
+    }
+}
+
+impl<T: 'static + Send + Copy + Num + CastFrom<f64>, E: 'static + Enum> Module for Gain<T, E> {
+    type Sample = T;
+    type Inputs = E;
+    type Outputs = E;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.gain.set_sample_rate(stream_data.sample_rate as _);
+    }
+
+    fn reset(&mut self) {
+        self.gain.reset(self.gain.target());
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        input_latencies
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let block_size = inputs.first().map_or(0, |x| x.len());
+        for i in 0..block_size {
+            let gain = self.gain.next_value();
+            for e in enum_iter::<E>() {
+                outputs[e.cast()][i] = gain * inputs[e.cast()][i];
+            }
+        }
+        ProcessStatus::Running
+    }
+}
+
+impl<T: Copy + Cast<f32>, E> GetParameter for Gain<T, E> {
+    type Param = Sequential<U1>;
+
+    fn get_param_raw(&self, _: Self::Param) -> Value<'_> {
+        Value::Float(self.gain.target().cast())
+    }
+}
+
+impl<T: Copy + Num + Cast<f32> + CastFrom<f64>, E> SetParameter for Gain<T, E> {
+    fn set_param_raw(&mut self, _: Self::Param, value: Value) {
+        if let Ok(gain) = f32::try_from(value) {
+            self.set_gain(gain);
+        }
+    }
+}
+
+/// A module panning a mono signal into a stereo signal, using a constant-power pan law.
+///
+/// The pan position, in -1..1 (from left to right), is its only parameter.
+#[derive(Debug, Clone)]
+pub struct Pan<T> {
+    pan: Smoothed<T>,
+}
+
+impl<T: Float + FloatConst + CastFrom<f64>> Pan<T> {
+    /// Creates a new pan module with the given initial pan position, in -1..1.
+    pub fn new(sample_rate: f64, pan: f32) -> Self {
+        Self {
+            pan: Smoothed::new(
+                sample_rate as _,
+                SMOOTHING_TIME,
+                T::cast_from(pan.clamp(-1., 1.) as f64),
+            ),
+        }
+    }
+
+    /// Sets the target pan position, clamped to -1..1.
+    pub fn set_pan(&mut self, pan: f32) {
+        self.pan.set_target(T::cast_from(pan.clamp(-1., 1.) as f64));
+    }
+
+    /// Computes the left and right gains of a pan position, such that their power sums to 1. Pan
+    /// positions outside of `-1..=1` are clamped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use approx::assert_relative_eq;
+    /// use clogbox_core::module::utilitarian::Pan;
+    /// let (left, right) = Pan::<f32>::gains(0.0);
+    /// assert_relative_eq!(left, right);
+    /// assert_relative_eq!(1.0, left * left + right * right);
+    /// assert_eq!((1.0, 0.0), Pan::<f32>::gains(-1.0));
+    /// ```
+    pub fn gains(pan: T) -> (T, T) {
+        // Both gains are computed the same way, so that they are symmetric and never negative
+        let one = T::one();
+        let pan = pan.max(-one).min(one);
+        let gain = |x: T| (x * T::FRAC_PI_4()).sin();
+        (gain(one - pan), gain(one + pan))
+    }
+}
+
+impl<T: 'static + Send + Float + FloatConst + CastFrom<f64>> Module for Pan<T> {
+    type Sample = T;
+    type Inputs = Sequential<U1>;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.pan.set_sample_rate(stream_data.sample_rate as _);
+    }
+
+    fn reset(&mut self) {
+        self.pan.reset(self.pan.target());
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|_| input_latencies[seq(0)])
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let (out_left, out_right) = outputs.split_at_mut(1);
+        let samples = out_left[0]
+            .iter_mut()
+            .zip(out_right[0].iter_mut())
+            .zip(inputs[0]);
+        for ((left, right), &x) in samples {
+            let (gain_left, gain_right) = Self::gains(self.pan.next_value());
+            *left = gain_left * x;
+            *right = gain_right * x;
+        }
+        ProcessStatus::Running
+    }
+}
+
+impl<T: Copy + Cast<f32>> GetParameter for Pan<T> {
+    type Param = Sequential<U1>;
+
+    fn get_param_raw(&self, _: Self::Param) -> Value<'_> {
+        Value::Float(self.pan.target().cast())
+    }
+}
+
+impl<T: Float + FloatConst + Cast<f32> + CastFrom<f64>> SetParameter for Pan<T> {
+    fn set_param_raw(&mut self, _: Self::Param, value: Value) {
+        if let Ok(pan) = f32::try_from(value) {
+            self.set_pan(pan);
+        }
+    }
+}
+
+/// A module summing all of its inputs into a single output, with a smoothed (linear) gain for
+/// each input.
+///
+/// Its parameters are the gains of each input.
+#[derive(Debug, Clone)]
+pub struct Mixer<T, In: Enum> {
+    gains: EnumMapArray<In, Smoothed<T>>,
+}
+
+impl<T: Copy + Num + CastFrom<f64>, In: Enum> Mixer<T, In> {
+    /// Creates a new mixer, with all input gains set to 1.
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            gains: EnumMapArray::new(|_| Smoothed::new(sample_rate as _, SMOOTHING_TIME, T::one())),
+        }
+    }
+
+    /// Sets the target gain of an input.
+    pub fn set_gain(&mut self, input: In, gain: f32) {
+        self.gains[input].set_target(T::cast_from(gain as f64));
+    }
+}
+
+impl<T: 'static + Send + Copy + NumAssign + CastFrom<f64>, In: 'static + Enum> Module
+    for Mixer<T, In>
+{
+    type Sample = T;
+    type Inputs = In;
+    type Outputs = Sequential<U1>;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        for gain in self.gains.values_mut() {
+            gain.set_sample_rate(stream_data.sample_rate as _);
+        }
+    }
+
+    fn reset(&mut self) {
+        for gain in self.gains.values_mut() {
+            gain.reset(gain.target());
+        }
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let latency = input_latencies.values().copied().fold(0., f64::max);
+        EnumMapArray::new(|_| latency)
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let output = &mut *outputs[0];
+        output.fill(T::zero());
+        for input in enum_iter::<In>() {
+            let gain = &mut self.gains[input];
+            for (out, &x) in output.iter_mut().zip(inputs[input.cast()]) {
+                *out += gain.next_value() * x;
+            }
+        }
+        ProcessStatus::Running
+    }
+}
+
+impl<T: Copy + Cast<f32>, In: Enum> GetParameter for Mixer<T, In> {
+    type Param = In;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        Value::Float(self.gains[param].target().cast())
+    }
+}
+
+impl<T: Copy + Num + Cast<f32> + CastFrom<f64>, In: Enum> SetParameter for Mixer<T, In> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        if let Ok(gain) = f32::try_from(value) {
+            self.set_gain(param, gain);
+        }
+    }
+}
+
+/// A module flushing subnormal (denormal) values of all of its channels to zero.
+///
+/// This is meant to be placed at the end of feedback paths, where decaying signals would otherwise
+/// end up as subnormal values, which are much slower to process.
+#[derive(Debug, Clone)]
+pub struct FlushDenormals<T, E>(PhantomData<fn(T) -> E>);
+
+impl<T, E> Default for FlushDenormals<T, E> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: 'static + Send + Float, E: 'static + Enum> Module for FlushDenormals<T, E> {
+    type Sample = T;
+    type Inputs = E;
+    type Outputs = E;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        input_latencies
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        for e in enum_iter::<E>() {
+            let (input, output) = (inputs[e.cast()], &mut *outputs[e.cast()]);
+            for (out, &x) in output.iter_mut().zip(input) {
+                *out = flush_denormal(x);
+            }
+        }
+        ProcessStatus::Running
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::module::utilitarian::SummingMatrix;
-    use crate::module::{Module, ProcessStatus, StreamData};
-    use crate::r#enum::enum_map::{EnumMap, EnumMapArray};
+    use crate::r#enum::enum_map::EnumMap;
     use crate::r#enum::{CartesianProduct, Enum};
     use approx::assert_relative_eq;
     use az::{Cast, CastFrom};
     use rstest::rstest;
     use std::borrow::Cow;
+    use std::f32::consts::FRAC_1_SQRT_2;
     
     use typenum::{Unsigned, U2};
 
@@ -255,7 +863,7 @@ mod tests {
     impl Enum for TestIn {
         type Count = U2;
 
-        fn name(&self) -> Cow<str> {
+        fn name(&self) -> Cow<'_, str> {
             match self {
                 Self::A => Cow::from("A"),
                 Self::B => Cow::from("B"),
@@ -291,7 +899,7 @@ mod tests {
     impl Enum for TestOut {
         type Count = U2;
 
-        fn name(&self) -> Cow<str> {
+        fn name(&self) -> Cow<'_, str> {
             match self {
                 Self::X => Cow::from("X"),
                 Self::Y => Cow::from("Y"),
@@ -318,4 +926,181 @@ mod tests {
 
         assert_relative_eq!(param_block.last_value(), 10.0);
     }
+
+    const STREAM_DATA: StreamData = StreamData::new(1000., 120., 20);
+
+    #[rstest]
+    fn test_gain_is_smoothed() {
+        let mut gain = Gain::<f32, TestIn>::new(STREAM_DATA.sample_rate, 1.);
+        gain.set_param(seq(0), 0.0f32);
+        let input = [1.; 20];
+        let (mut a, mut b) = ([0.; 20], [0.; 20]);
+        gain.process(&STREAM_DATA, &[&input, &input], &mut [&mut a, &mut b]);
+        assert_relative_eq!(0.9, a[0]);
+        assert_eq!(a, b);
+        assert!(a.windows(2).all(|w| w[1] <= w[0]));
+        assert_eq!(0., a[19]);
+    }
+
+    #[rstest]
+    #[case(-1., [1., 0.])]
+    #[case(0., [FRAC_1_SQRT_2, FRAC_1_SQRT_2])]
+    #[case(1., [0., 1.])]
+    fn test_constant_power_pan(#[case] pan: f32, #[case] expected: [f32; 2]) {
+        let mut module = Pan::<f32>::new(STREAM_DATA.sample_rate, pan);
+        let input = [1.; 20];
+        let (mut left, mut right) = ([0.; 20], [0.; 20]);
+        module.process(&STREAM_DATA, &[&input], &mut [&mut left, &mut right]);
+        assert_relative_eq!(expected[0], left[19], epsilon = 1e-6);
+        assert_relative_eq!(expected[1], right[19], epsilon = 1e-6);
+    }
+
+    #[rstest]
+    fn test_mixer_sums_inputs() {
+        let mut mixer = Mixer::<f32, TestIn>::new(STREAM_DATA.sample_rate);
+        mixer.set_gain(TestIn::B, 0.5);
+        mixer.reset();
+        let (a, b) = ([1.; 20], [2.; 20]);
+        let mut output = [0.; 20];
+        mixer.process(&STREAM_DATA, &[&a, &b], &mut [&mut output]);
+        assert_eq!([2.; 20], output);
+        assert_eq!(Value::Float(0.5), mixer.get_param_raw(TestIn::B));
+        let latency = mixer.latency(EnumMapArray::new(|i: TestIn| i.cast() as f64 + 1.));
+        assert_eq!(2., latency[seq(0)]);
+    }
+
+    #[rstest]
+    fn test_flush_denormals() {
+        let mut module = FlushDenormals::<f32, TestIn>::default();
+        let a = [1e-40, -1e-40, 1e-30, 0.5];
+        let (mut x, mut y) = ([1.; 4], [1.; 4]);
+        module.process(&STREAM_DATA, &[&a, &a], &mut [&mut x, &mut y]);
+        assert_eq!([0., 0., 1e-30, 0.5], x);
+        assert_eq!(x, y);
+    }
+
+    #[rstest]
+    fn test_chain_connects_matching_ports() {
+        let mut chain = Chain::new(
+            Gain::<f32, TestIn>::new(STREAM_DATA.sample_rate, 0.5),
+            Mixer::<f32, TestIn>::new(STREAM_DATA.sample_rate),
+        );
+        chain.reallocate(STREAM_DATA);
+        assert!(chain.supports_stream(STREAM_DATA));
+        let (a, b) = ([1.; 20], [2.; 20]);
+        let mut output = [0.; 20];
+        chain.process(&STREAM_DATA, &[&a, &b], &mut [&mut output]);
+        assert_eq!([1.5; 20], output);
+        let latency = chain.latency(EnumMapArray::new(|i: TestIn| i.cast() as f64));
+        assert_eq!(1., latency[seq(0)]);
+    }
+
+    #[rstest]
+    fn test_parallel_sum_adds_outputs() {
+        let mut module = ParallelSum::new(
+            Gain::<f32, TestIn>::new(STREAM_DATA.sample_rate, 0.5),
+            Gain::<f32, TestIn>::new(STREAM_DATA.sample_rate, 2.),
+        );
+        module.reallocate(STREAM_DATA);
+        let (a, b) = ([1.; 20], [-1.; 20]);
+        let (mut x, mut y) = ([0.; 20], [0.; 20]);
+        module.process(&STREAM_DATA, &[&a, &b], &mut [&mut x, &mut y]);
+        assert_eq!([2.5; 20], x);
+        assert_eq!([-2.5; 20], y);
+    }
+
+    /// Test module delaying its inputs by 2 samples, and reporting the matching latency.
+    #[derive(Debug, Clone, Default)]
+    struct TwoSampleDelay([[f32; 2]; 2]);
+
+    impl Module for TwoSampleDelay {
+        type Sample = f32;
+        type Inputs = TestIn;
+        type Outputs = TestIn;
+
+        fn supports_stream(&self, _: StreamData) -> bool {
+            true
+        }
+
+        fn latency(
+            &self,
+            input_latencies: EnumMapArray<Self::Inputs, f64>,
+        ) -> EnumMapArray<Self::Outputs, f64> {
+            EnumMapArray::new(|k| input_latencies[k] + 2.)
+        }
+
+        fn process(
+            &mut self,
+            _: &StreamData,
+            inputs: &[&[Self::Sample]],
+            outputs: &mut [&mut [Self::Sample]],
+        ) -> ProcessStatus {
+            for ((state, input), output) in self.0.iter_mut().zip(inputs).zip(outputs) {
+                for (o, &x) in output.iter_mut().zip(input.iter()) {
+                    *o = state[0];
+                    *state = [state[1], x];
+                }
+            }
+            ProcessStatus::Running
+        }
+    }
+
+    #[rstest]
+    #[case(true)]
+    #[case(false)]
+    fn test_parallel_sum_compensates_latency(#[case] delayed_first: bool) {
+        let gain = Gain::<f32, TestIn>::new(STREAM_DATA.sample_rate, 1.);
+        let mut module: Box<dyn Module<Sample = f32, Inputs = TestIn, Outputs = TestIn>> =
+            if delayed_first {
+                Box::new(ParallelSum::new(TwoSampleDelay::default(), gain))
+            } else {
+                Box::new(ParallelSum::new(gain, TwoSampleDelay::default()))
+            };
+        module.reallocate(STREAM_DATA);
+        assert_eq!(
+            EnumMapArray::new(|_| 3.),
+            module.latency(EnumMapArray::new(|_| 1.))
+        );
+
+        let mut impulse = [0.; 20];
+        impulse[0] = 1.;
+        let (mut x, mut y) = ([0.; 20], [0.; 20]);
+        module.process(&STREAM_DATA, &[&impulse, &[0.; 20]], &mut [&mut x, &mut y]);
+        let mut expected = [0.; 20];
+        expected[2] = 2.;
+        assert_eq!(expected, x);
+        assert_eq!([0.; 20], y);
+    }
+
+    #[rstest]
+    #[case(-3.0, -1.0)]
+    #[case(-1.5, -1.0)]
+    #[case(1.5, 1.0)]
+    #[case(3.0, 1.0)]
+    fn test_pan_gains_clamp(#[case] pan: f32, #[case] clamped: f32) {
+        let (left, right) = Pan::<f32>::gains(pan);
+        assert_eq!(Pan::<f32>::gains(clamped), (left, right));
+        assert_eq!((right, left), Pan::<f32>::gains(-pan));
+        assert!(left >= 0. && right >= 0.);
+        assert_relative_eq!(1.0, left * left + right * right, epsilon = 1e-6);
+    }
+
+    #[rstest]
+    fn test_f64_processing() {
+        let stream_data = StreamData {
+            block_size: 4,
+            ..STREAM_DATA
+        };
+        let mut module = Chain::new(
+            Pan::<f64>::new(stream_data.sample_rate, 0.),
+            Mixer::<f64, Stereo>::new(stream_data.sample_rate),
+        );
+        module.reallocate(stream_data);
+        let mut output = [0.; 4];
+        module.process(&stream_data, &[&[0.5; 4]], &mut [&mut output]);
+        // Gains computed in f32 would be off by about 1e-8
+        for x in output {
+            assert_relative_eq!(std::f64::consts::FRAC_1_SQRT_2, x, epsilon = 1e-15);
+        }
+    }
 }
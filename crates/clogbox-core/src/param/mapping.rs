@@ -0,0 +1,112 @@
+//! Sampled mappings between normalized and display values of parameters.
+//!
+//! [`DisplayCurve`] holds a sampled version of the mapping from the normalized (`0..1`) value of
+//! a parameter to its displayed value, as given by
+//! [`NormalizeParameter::display_curve`](crate::param::NormalizeParameter::display_curve). This
+//! lets generic UIs and hosts draw the actual shape of logarithmic or exponential parameters, and
+//! convert values both ways without knowing the concrete type of the parameter.
+//!
+//! # Example
+//!
+//! ```
+//! use clogbox_core::param::mapping::DisplayCurve;
+//!
+//! // Logarithmic frequency parameter, from 20 Hz to 20 kHz
+//! let curve = DisplayCurve::sample(64, |x| 20.0 * 1000f32.powf(x));
+//! assert_eq!(64, curve.points().len());
+//! assert!((curve.to_display(0.5) - 632.5).abs() < 5.0);
+//! assert!((curve.to_normalized(632.5) - 0.5).abs() < 1e-2);
+//! ```
+
+/// A mapping from normalized to display values, sampled at regular normalized intervals.
+///
+/// Values between samples are linearly interpolated. The inverse mapping assumes the curve is
+/// monotonic, which is the case for all well-behaved parameter mappings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayCurve {
+    points: Box<[(f32, f32)]>,
+}
+
+impl DisplayCurve {
+    /// Samples the given normalized-to-display mapping at `points` regularly spaced normalized
+    /// values, from 0 to 1 inclusive.
+    ///
+    /// # Arguments
+    ///
+    /// * `points` - Number of samples of the curve, at least 2.
+    /// * `to_display` - Function mapping a normalized value to its displayed value.
+    pub fn sample(points: usize, to_display: impl FnMut(f32) -> f32) -> Self {
+        let points = points.max(2);
+        let last = (points - 1) as f32;
+        Self::from_points((0..points).map(|i| i as f32 / last).map(to_display))
+            .expect("Curve has at least 2 points")
+    }
+
+    /// Creates a curve from display values sampled at regularly spaced normalized values, from 0
+    /// to 1 inclusive. Returns `None` if fewer than 2 values are given.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::param::mapping::DisplayCurve;
+    /// let curve = DisplayCurve::from_points([0.0, 10.0, 100.0]).unwrap();
+    /// assert_eq!(55.0, curve.to_display(0.75));
+    /// assert!(DisplayCurve::from_points([1.0]).is_none());
+    /// ```
+    pub fn from_points(values: impl IntoIterator<Item = f32>) -> Option<Self> {
+        let values = Vec::from_iter(values);
+        if values.len() < 2 {
+            return None;
+        }
+        let last = (values.len() - 1) as f32;
+        let points = values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (i as f32 / last, value))
+            .collect();
+        Some(Self { points })
+    }
+
+    /// Returns the sampled points of the curve, as `(normalized, display)` pairs.
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    /// Maps a normalized value, clamped to `0..1`, to its displayed value.
+    pub fn to_display(&self, normalized: f32) -> f32 {
+        let position = normalized.clamp(0., 1.) * (self.points.len() - 1) as f32;
+        let index = (position.floor() as usize).min(self.points.len() - 2);
+        let fract = position - index as f32;
+        let (a, b) = (self.points[index].1, self.points[index + 1].1);
+        a + (b - a) * fract
+    }
+
+    /// Maps a displayed value back to its normalized value. Values outside the range of the curve
+    /// are clamped to its ends.
+    pub fn to_normalized(&self, display: f32) -> f32 {
+        let first = self.points[0].1;
+        let last = self.points[self.points.len() - 1].1;
+        let increasing = last >= first;
+        // Index of the first point past the display value, in the direction of the curve
+        let index = self.points.partition_point(|&(_, value)| {
+            if increasing {
+                value <= display
+            } else {
+                value >= display
+            }
+        });
+        match index {
+            0 => 0.,
+            i if i == self.points.len() => 1.,
+            i => {
+                let (x0, y0) = self.points[i - 1];
+                let (x1, y1) = self.points[i];
+                if y1 == y0 {
+                    x0
+                } else {
+                    x0 + (x1 - x0) * (display - y0) / (y1 - y0)
+                }
+            }
+        }
+    }
+}
@@ -1,25 +1,31 @@
-/// This module provides a comprehensive abstraction for various types of values 
-/// that can be used across the application. It offers an enumeration of types 
-/// including integers, floats, strings, binary data, and arrays.
-///
-/// The module also implements conversion traits to and from these types, 
-/// enabling seamless transitions between different value representations. 
-/// Errors in conversions are handled gracefully with a specific error type 
-/// that provides detailed mismatch information.
-///
-/// # Example
-///
-/// ```
-/// use clogbox_core::param::value::Value;
-///
-/// let int_value = Value::from(42);
-/// if let Value::Int(i) = int_value {
-///     println!("Integer value: {}", i);
-/// }
-/// ```
+//! This module provides a comprehensive abstraction for various types of values 
+//! that can be used across the application. It offers an enumeration of types 
+//! including integers, floats, strings, binary data, and arrays.
+//!
+//! The module also implements conversion traits to and from these types, 
+//! enabling seamless transitions between different value representations. 
+//! Errors in conversions are handled gracefully with a specific error type 
+//! that provides detailed mismatch information.
+//!
+//! # Example
+//!
+//! ```
+//! use clogbox_core::param::value::Value;
+//!
+//! let int_value = Value::from(42);
+//! if let Value::Int(i) = int_value {
+//!     println!("Integer value: {}", i);
+//! }
+//! ```
 pub mod value;
 pub mod curve;
+pub mod mapping;
+pub mod precision;
+pub mod smoothed;
+pub mod spline;
 
+use crate::param::mapping::DisplayCurve;
+use crate::param::precision::ParamPrecision;
 use crate::param::value::Value;
 use crate::r#enum::Enum;
 
@@ -37,7 +43,7 @@ pub trait GetParameter {
     /// # Returns
     /// 
     /// - A `Value` corresponding to the provided parameter.
-    fn get_param_raw(&self, param: Self::Param) -> Value;
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_>;
 
     /// Gets the parameter as a certain type.
     ///
@@ -109,4 +115,39 @@ pub trait NormalizeParameter {
     /// - `param`: The parameter to unnormalize.
     /// - `value`: The value to unnormalize.
     fn unnormalize_param<'a>(&self, param: Self::Param, value: f32) -> Option<Value<'a>>;
+
+    /// Returns the display precision and increments of a parameter, used when formatting its
+    /// value, or when nudging it by coarse or fine steps.
+    ///
+    /// # Parameters
+    ///
+    /// - `param`: The parameter to get the precision metadata of.
+    #[allow(unused_variables)]
+    fn param_precision(&self, param: Self::Param) -> ParamPrecision {
+        ParamPrecision::default()
+    }
+
+    /// Returns the mapping from normalized values to displayed values of a parameter, sampled at
+    /// `points` regularly spaced normalized values, so that UIs can draw the actual shape of the
+    /// mapping and convert values both ways.
+    ///
+    /// The default implementation samples [`Self::unnormalize_param`], and returns `None` if any
+    /// of the unnormalized values is not a number.
+    ///
+    /// # Parameters
+    ///
+    /// - `param`: The parameter to sample the mapping of.
+    /// - `points`: Number of samples of the mapping, at least 2.
+    fn display_curve(&self, param: Self::Param, points: usize) -> Option<DisplayCurve> {
+        let points = points.max(2);
+        let last = (points - 1) as f32;
+        let values = (0..points).map(|i| {
+            match self.unnormalize_param(param, i as f32 / last)? {
+                Value::Float(value) => Some(value),
+                Value::Int(value) => Some(value as f32),
+                _ => None,
+            }
+        });
+        DisplayCurve::from_points(values.collect::<Option<Vec<_>>>()?)
+    }
 }
\ No newline at end of file
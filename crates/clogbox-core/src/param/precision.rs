@@ -0,0 +1,155 @@
+//! Display precision and increment metadata for parameters.
+//!
+//! [`ParamPrecision`] describes how a parameter value should be displayed (number of decimals),
+//! and how it should be nudged by coarse and fine increments, for example from keyboard input or
+//! knob scrolling. Discrete parameters additionally snap their values to their fine increment, so
+//! that parameters like sample counts or semitones always land on whole steps.
+//!
+//! # Example
+//!
+//! ```
+//! use clogbox_core::param::precision::ParamPrecision;
+//!
+//! let semitones = ParamPrecision::discrete(1.0).with_coarse_step(12.0);
+//! assert_eq!("3", semitones.format_value(3.2));
+//! assert_eq!(15.0, semitones.nudge(3.2, 1, false));
+//! assert_eq!(2.0, semitones.nudge(3.2, -1, true));
+//! ```
+
+/// Display and increment metadata of a parameter.
+///
+/// Increments are expressed in the unit of the parameter (that is, on unnormalized values).
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ParamPrecision {
+    /// Number of decimals to show when displaying the value.
+    pub decimals: usize,
+    /// Increment used for coarse adjustments.
+    pub coarse_step: f32,
+    /// Increment used for fine adjustments.
+    pub fine_step: f32,
+    /// Whether values snap to multiples of the fine increment.
+    pub discrete: bool,
+}
+
+impl Default for ParamPrecision {
+    /// Default precision, suitable for continuous parameters within the `0..1` range.
+    fn default() -> Self {
+        Self::continuous(2, 0.1, 0.01)
+    }
+}
+
+impl ParamPrecision {
+    /// Creates the precision metadata of a continuous parameter.
+    ///
+    /// # Arguments
+    ///
+    /// * `decimals` - Number of decimals to show when displaying the value.
+    /// * `coarse_step` - Increment used for coarse adjustments.
+    /// * `fine_step` - Increment used for fine adjustments.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::param::precision::ParamPrecision;
+    /// let cutoff = ParamPrecision::continuous(0, 100.0, 1.0);
+    /// assert_eq!("1235", cutoff.format_value(1234.56));
+    /// ```
+    pub const fn continuous(decimals: usize, coarse_step: f32, fine_step: f32) -> Self {
+        Self {
+            decimals,
+            coarse_step,
+            fine_step,
+            discrete: false,
+        }
+    }
+
+    /// Creates the precision metadata of a discrete parameter, whose values snap to multiples of
+    /// `step`. The coarse increment is set to the same step, and the number of decimals is
+    /// derived from it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::param::precision::ParamPrecision;
+    /// let samples = ParamPrecision::discrete(1.0);
+    /// assert_eq!(0, samples.decimals);
+    /// let quarter_tones = ParamPrecision::discrete(0.5);
+    /// assert_eq!(1, quarter_tones.decimals);
+    /// ```
+    pub fn discrete(step: f32) -> Self {
+        Self {
+            decimals: decimals_for_step(step),
+            coarse_step: step,
+            fine_step: step,
+            discrete: true,
+        }
+    }
+
+    /// Returns this precision metadata with the given coarse increment.
+    pub const fn with_coarse_step(mut self, coarse_step: f32) -> Self {
+        self.coarse_step = coarse_step;
+        self
+    }
+
+    /// Returns this precision metadata with the given number of displayed decimals.
+    pub const fn with_decimals(mut self, decimals: usize) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Formats the given value with the number of decimals of this parameter. Discrete values are
+    /// snapped before being formatted.
+    pub fn format_value(&self, value: f32) -> String {
+        format!("{:.*}", self.decimals, self.snap(value))
+    }
+
+    /// Snaps the value to a multiple of the fine increment if the parameter is discrete, or
+    /// returns it unchanged otherwise.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::param::precision::ParamPrecision;
+    /// assert_eq!(4.0, ParamPrecision::discrete(2.0).snap(4.9));
+    /// assert_eq!(4.9, ParamPrecision::default().snap(4.9));
+    /// ```
+    pub fn snap(&self, value: f32) -> f32 {
+        if self.discrete && self.fine_step > 0.0 {
+            (value / self.fine_step).round() * self.fine_step
+        } else {
+            value
+        }
+    }
+
+    /// Moves the value by the given number of coarse or fine increments.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value to nudge.
+    /// * `steps` - Number of increments to move the value by; negative values decrease it.
+    /// * `fine` - Whether to use the fine increment instead of the coarse one.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use clogbox_core::param::precision::ParamPrecision;
+    /// let precision = ParamPrecision::default();
+    /// assert_eq!(0.7, precision.nudge(0.5, 2, false));
+    /// ```
+    pub fn nudge(&self, value: f32, steps: i32, fine: bool) -> f32 {
+        let step = if fine { self.fine_step } else { self.coarse_step };
+        self.snap(self.snap(value) + steps as f32 * step)
+    }
+}
+
+/// Returns the minimum number of decimals needed to display multiples of `step`, up to 6.
+fn decimals_for_step(step: f32) -> usize {
+    const MAX_DECIMALS: usize = 6;
+    let step = step.abs() as f64;
+    (0..MAX_DECIMALS)
+        .find(|&decimals| {
+            let scaled = step * 10f64.powi(decimals as _);
+            (scaled - scaled.round()).abs() < 1e-6 * scaled.max(1.0)
+        })
+        .unwrap_or(MAX_DECIMALS)
+}
@@ -0,0 +1,171 @@
+//! This module provides a smoother for parameter values changed from outside the audio stream.
+//!
+//! Setting a parameter directly to a new value creates a discontinuity in the processed signal, which
+//! is heard as a click or as "zipper" noise. A [`Smoothed`] value instead ramps linearly to its
+//! new target over a fixed amount of time, and is advanced once per processed sample.
+//!
+//! The value is stored at the precision of its type parameter (`f32` by default), so that modules
+//! processing `f64` samples can smooth their parameters at full precision.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::param::smoothed::Smoothed;
+//!
+//! let mut gain = Smoothed::new(1000.0, 0.004, 0.0);
+//! gain.set_target(1.0);
+//! let ramp: Vec<f32> = (0..5).map(|_| gain.next_value()).collect();
+//! assert_eq!(vec![0.25, 0.5, 0.75, 1.0, 1.0], ramp);
+//! ```
+use az::CastFrom;
+use num_traits::Num;
+
+/// A parameter value which linearly ramps towards its target.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Smoothed<T = f32> {
+    current: T,
+    target: T,
+    step: T,
+    remaining: usize,
+    sample_rate: f32,
+    ramp_time: f32,
+}
+
+impl<T: Copy + Num + CastFrom<f64>> Smoothed<T> {
+    /// Creates a new smoothed value.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - The sample rate at which the value is advanced.
+    /// * `ramp_time` - Time (in seconds) taken to reach a new target.
+    /// * `initial_value` - Initial value, which is also the initial target.
+    pub fn new(sample_rate: f32, ramp_time: f32, initial_value: T) -> Self {
+        Self {
+            current: initial_value,
+            target: initial_value,
+            step: T::zero(),
+            remaining: 0,
+            sample_rate,
+            ramp_time: ramp_time.max(0.),
+        }
+    }
+
+    /// Sets the sample rate at which the value is advanced. An ongoing ramp is completed
+    /// immediately.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.reset(self.target);
+    }
+
+    /// Sets the time (in seconds) taken to reach a new target. This applies to the next target.
+    pub fn set_ramp_time(&mut self, ramp_time: f32) {
+        self.ramp_time = ramp_time.max(0.);
+    }
+
+    /// Sets a new target for the value to ramp towards.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_core::param::smoothed::Smoothed;
+    /// let mut value = Smoothed::new(100.0, 0.1, 0.0);
+    /// value.set_target(1.0);
+    /// assert!(value.is_smoothing());
+    /// assert_eq!(0.1, value.next_value());
+    /// ```
+    pub fn set_target(&mut self, target: T) {
+        self.target = target;
+        self.remaining = (self.ramp_time * self.sample_rate).round() as usize;
+        if self.remaining == 0 {
+            self.current = target;
+            self.step = T::zero();
+        } else {
+            self.step = (target - self.current) / T::cast_from(self.remaining as f64);
+        }
+    }
+
+    /// Immediately sets the value and its target, cancelling any ongoing ramp.
+    pub fn reset(&mut self, value: T) {
+        self.current = value;
+        self.target = value;
+        self.step = T::zero();
+        self.remaining = 0;
+    }
+
+    /// Advances the value by one sample, and returns the new value.
+    #[inline]
+    pub fn next_value(&mut self) -> T {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.current = if self.remaining == 0 {
+                self.target
+            } else {
+                self.current + self.step
+            };
+        }
+        self.current
+    }
+}
+
+impl<T: Copy> Smoothed<T> {
+    /// Returns the current value, without advancing it.
+    pub fn current(&self) -> T {
+        self.current
+    }
+
+    /// Returns the target of the value.
+    pub fn target(&self) -> T {
+        self.target
+    }
+
+    /// Returns whether the value is still ramping towards its target.
+    pub fn is_smoothing(&self) -> bool {
+        self.remaining > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_reaches_target_exactly() {
+        let mut value = Smoothed::new(44100., 0.01, 0.);
+        value.set_target(0.3);
+        let mut last = 0.;
+        for _ in 0..441 {
+            let next = value.next_value();
+            assert!(next >= last);
+            last = next;
+        }
+        assert_eq!(0.3, last);
+        assert!(!value.is_smoothing());
+    }
+
+    #[rstest]
+    fn test_retarget_during_ramp() {
+        let mut value = Smoothed::new(10., 0.4, 0.);
+        value.set_target(4.);
+        assert_eq!(1., value.next_value());
+        value.set_target(-1.);
+        let ramp: Vec<f32> = (0..4).map(|_| value.next_value()).collect();
+        assert_eq!(vec![0.5, 0., -0.5, -1.], ramp);
+    }
+
+    #[rstest]
+    fn test_f64_ramp() {
+        let mut value = Smoothed::<f64>::new(3., 1., 0.);
+        value.set_target(1e-10);
+        let ramp: Vec<f64> = (0..3).map(|_| value.next_value()).collect();
+        assert_eq!(vec![1e-10 / 3., 2e-10 / 3., 1e-10], ramp);
+    }
+
+    #[rstest]
+    fn test_no_ramp_time_jumps() {
+        let mut value = Smoothed::new(44100., 0., 0.);
+        value.set_target(1.);
+        assert_eq!(1., value.current());
+        assert!(!value.is_smoothing());
+    }
+}
@@ -0,0 +1,435 @@
+//! This module provides a custom curve parameter type, defined by editable breakpoints.
+//!
+//! [`SplineCurve`] stores a set of breakpoints, and evaluates a smooth curve passing through them
+//! using monotone cubic Hermite interpolation, which never overshoots between breakpoints. This
+//! makes it suitable for velocity curves, waveshaper transfer functions or envelope shapes.
+//!
+//! Editing the curve is done on the main thread, while evaluating it is allocation-free and can
+//! be done from the audio thread. Curves can be serialized into an opaque binary blob, which can
+//! be passed around as a [`Value::Binary`] or stored in the state of a module.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::param::spline::SplineCurve;
+//!
+//! let mut curve = SplineCurve::identity();
+//! curve.insert(0.5, 0.8).unwrap();
+//! assert_eq!(0.8, curve.evaluate(0.5));
+//!
+//! let bytes = curve.to_bytes();
+//! let restored = SplineCurve::from_bytes(&bytes).unwrap();
+//! assert_eq!(curve, restored);
+//! ```
+use crate::math::interpolation::hermite_interpolate;
+use crate::param::value::{TryFromValueError, Value};
+use thiserror::Error;
+
+/// A single breakpoint of a [`SplineCurve`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Breakpoint {
+    /// Position of the breakpoint on the horizontal axis.
+    pub x: f32,
+    /// Value of the curve at the breakpoint.
+    pub y: f32,
+}
+
+impl Breakpoint {
+    fn finite(x: f32, y: f32) -> Result<Self, SplineCurveError> {
+        let point = Self { x, y };
+        if x.is_finite() && y.is_finite() {
+            Ok(point)
+        } else {
+            Err(SplineCurveError::NonFinite(point))
+        }
+    }
+}
+
+/// Error type returned when deserializing a [`SplineCurve`] fails.
+#[derive(Debug, Clone, Error)]
+pub enum SplineCurveError {
+    /// The serialized data was written with an unsupported format version.
+    #[error("Unsupported spline curve format version {0}")]
+    UnsupportedVersion(u8),
+    /// The serialized data does not have the length its header specifies.
+    #[error("Invalid spline curve data length: expected {expected} bytes, got {found}")]
+    InvalidLength {
+        /// The expected length, in bytes.
+        expected: usize,
+        /// The found length, in bytes.
+        found: usize,
+    },
+    /// A breakpoint has a non-finite (infinite or NaN) coordinate.
+    #[error("Non-finite spline curve breakpoint ({}, {})", .0.x, .0.y)]
+    NonFinite(Breakpoint),
+    /// The value holding the serialized curve is not a binary value.
+    #[error(transparent)]
+    Value(#[from] TryFromValueError),
+}
+
+/// A curve defined by breakpoints, and smoothly interpolated between them.
+///
+/// Breakpoints are kept sorted by their horizontal position, and have finite coordinates.
+/// Evaluating the curve outside of the breakpoints range returns the value of the nearest
+/// breakpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplineCurve {
+    points: Vec<Breakpoint>,
+    tangents: Vec<f32>,
+}
+
+impl Default for SplineCurve {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl SplineCurve {
+    const FORMAT_VERSION: u8 = 1;
+    const HEADER_LEN: usize = 5;
+    const POINT_LEN: usize = 8;
+
+    /// Creates a new curve from the given breakpoints, which don't need to be sorted.
+    ///
+    /// Returns [`SplineCurveError::NonFinite`] if any breakpoint has an infinite or NaN
+    /// coordinate.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_core::param::spline::SplineCurve;
+    /// let curve = SplineCurve::new([(1.0, 1.0), (0.0, 0.0)]).unwrap();
+    /// assert_eq!(0.0, curve.points()[0].x);
+    /// assert!(SplineCurve::new([(0.0, 0.0), (f32::NAN, 1.0)]).is_err());
+    /// ```
+    pub fn new(points: impl IntoIterator<Item = (f32, f32)>) -> Result<Self, SplineCurveError> {
+        let mut points = points
+            .into_iter()
+            .map(|(x, y)| Breakpoint::finite(x, y))
+            .collect::<Result<Vec<_>, _>>()?;
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        let mut this = Self {
+            tangents: vec![0.0; points.len()],
+            points,
+        };
+        this.update_tangents();
+        Ok(this)
+    }
+
+    /// Creates a linear curve going from (0, 0) to (1, 1).
+    pub fn identity() -> Self {
+        Self::new([(0.0, 0.0), (1.0, 1.0)]).expect("Identity breakpoints are finite")
+    }
+
+    /// Returns the breakpoints of this curve, sorted by their horizontal position.
+    pub fn points(&self) -> &[Breakpoint] {
+        &self.points
+    }
+
+    /// Inserts a new breakpoint, returning its index, or [`SplineCurveError::NonFinite`] if it has
+    /// an infinite or NaN coordinate.
+    pub fn insert(&mut self, x: f32, y: f32) -> Result<usize, SplineCurveError> {
+        let point = Breakpoint::finite(x, y)?;
+        let index = self.points.partition_point(|p| p.x <= x);
+        self.points.insert(index, point);
+        self.tangents.insert(index, 0.0);
+        self.update_tangents();
+        Ok(index)
+    }
+
+    /// Removes the breakpoint at the given index, returning it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn remove(&mut self, index: usize) -> Breakpoint {
+        let point = self.points.remove(index);
+        self.tangents.remove(index);
+        self.update_tangents();
+        point
+    }
+
+    /// Moves the breakpoint at the given index. The new horizontal position is clamped between the
+    /// neighboring breakpoints, so that breakpoints keep their order.
+    ///
+    /// Returns [`SplineCurveError::NonFinite`], leaving the curve untouched, if the new position
+    /// has an infinite or NaN coordinate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_core::param::spline::SplineCurve;
+    /// let mut curve = SplineCurve::new([(0.0, 0.0), (0.5, 0.5), (1.0, 1.0)]).unwrap();
+    /// curve.move_point(1, 2.0, 0.25).unwrap();
+    /// assert_eq!(1.0, curve.points()[1].x);
+    /// ```
+    pub fn move_point(&mut self, index: usize, x: f32, y: f32) -> Result<(), SplineCurveError> {
+        let Breakpoint { x, y } = Breakpoint::finite(x, y)?;
+        let min = index
+            .checked_sub(1)
+            .map(|i| self.points[i].x)
+            .unwrap_or(f32::NEG_INFINITY);
+        let max = self
+            .points
+            .get(index + 1)
+            .map(|p| p.x)
+            .unwrap_or(f32::INFINITY);
+        self.points[index] = Breakpoint {
+            x: x.clamp(min, max),
+            y,
+        };
+        self.update_tangents();
+        Ok(())
+    }
+
+    /// Evaluates the curve at the given horizontal position.
+    ///
+    /// This method does not allocate, and can be used from the audio thread. An empty curve always
+    /// evaluates to 0, and a NaN position evaluates to the value of the first breakpoint.
+    pub fn evaluate(&self, x: f32) -> f32 {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0,
+        };
+        if x.is_nan() || x <= first.x {
+            return first.y;
+        }
+        if x >= last.x {
+            return last.y;
+        }
+
+        let i = self.points.partition_point(|p| p.x <= x) - 1;
+        let (a, b) = (self.points[i], self.points[i + 1]);
+        let h = b.x - a.x;
+        if h <= 0.0 {
+            return b.y;
+        }
+        let t = (x - a.x) / h;
+        hermite_interpolate(a.y, h * self.tangents[i], b.y, h * self.tangents[i + 1], t)
+    }
+
+    /// Serializes the curve into an opaque binary blob, which can be restored with
+    /// [`Self::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::HEADER_LEN + Self::POINT_LEN * self.points.len());
+        bytes.push(Self::FORMAT_VERSION);
+        bytes.extend_from_slice(&(self.points.len() as u32).to_le_bytes());
+        for point in &self.points {
+            bytes.extend_from_slice(&point.x.to_le_bytes());
+            bytes.extend_from_slice(&point.y.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserializes a curve previously serialized with [`Self::to_bytes`]. Curves with non-finite
+    /// breakpoints are rejected.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SplineCurveError> {
+        let invalid_length = |expected| SplineCurveError::InvalidLength {
+            expected,
+            found: bytes.len(),
+        };
+        let (&version, rest) = bytes
+            .split_first()
+            .ok_or_else(|| invalid_length(Self::HEADER_LEN))?;
+        if version != Self::FORMAT_VERSION {
+            return Err(SplineCurveError::UnsupportedVersion(version));
+        }
+        let (count, points) = rest
+            .split_first_chunk::<4>()
+            .ok_or_else(|| invalid_length(Self::HEADER_LEN))?;
+        let count = u32::from_le_bytes(*count) as usize;
+        // The count is untrusted, and the expected length can overflow on 32-bit targets
+        let expected = count
+            .checked_mul(Self::POINT_LEN)
+            .and_then(|n| n.checked_add(Self::HEADER_LEN))
+            .ok_or_else(|| invalid_length(usize::MAX))?;
+        if bytes.len() != expected {
+            return Err(invalid_length(expected));
+        }
+
+        let read_f32 = |bytes: &[u8]| f32::from_le_bytes(bytes.try_into().unwrap());
+        Self::new(points.chunks_exact(Self::POINT_LEN).map(|chunk| {
+            let (x, y) = chunk.split_at(4);
+            (read_f32(x), read_f32(y))
+        }))
+    }
+
+    /// Computes the tangents at each breakpoint, following the Fritsch-Carlson method, which
+    /// preserves monotonicity between breakpoints.
+    fn update_tangents(&mut self) {
+        let n = self.points.len();
+        self.tangents.resize(n, 0.0);
+        if n < 2 {
+            self.tangents.fill(0.0);
+            return;
+        }
+
+        let secant = |i: usize| {
+            let (a, b) = (self.points[i], self.points[i + 1]);
+            let h = b.x - a.x;
+            if h > 0.0 {
+                (b.y - a.y) / h
+            } else {
+                0.0
+            }
+        };
+
+        self.tangents[0] = secant(0);
+        self.tangents[n - 1] = secant(n - 2);
+        for i in 1..n - 1 {
+            let (d0, d1) = (secant(i - 1), secant(i));
+            self.tangents[i] = if d0 * d1 <= 0.0 { 0.0 } else { 0.5 * (d0 + d1) };
+        }
+
+        for i in 0..n - 1 {
+            let d = secant(i);
+            if d == 0.0 {
+                self.tangents[i] = 0.0;
+                self.tangents[i + 1] = 0.0;
+                continue;
+            }
+            let a = self.tangents[i] / d;
+            let b = self.tangents[i + 1] / d;
+            let s = a * a + b * b;
+            if s > 9.0 {
+                let t = 3.0 / s.sqrt();
+                self.tangents[i] = t * a * d;
+                self.tangents[i + 1] = t * b * d;
+            }
+        }
+    }
+}
+
+impl<'a> TryFrom<Value<'a>> for SplineCurve {
+    type Error = SplineCurveError;
+
+    fn try_from(value: Value<'a>) -> Result<Self, Self::Error> {
+        Self::from_bytes(<&[u8]>::try_from(value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_identity_is_linear() {
+        let curve = SplineCurve::identity();
+        for i in 0..=10 {
+            let x = i as f32 / 10.0;
+            assert_relative_eq!(x, curve.evaluate(x), epsilon = 1e-6);
+        }
+    }
+
+    #[rstest]
+    fn test_passes_through_breakpoints() {
+        let curve = SplineCurve::new([(0.0, 0.0), (0.25, 0.7), (0.5, 0.2), (1.0, 1.0)]).unwrap();
+        for p in curve.points() {
+            assert_relative_eq!(p.y, curve.evaluate(p.x), epsilon = 1e-6);
+        }
+    }
+
+    #[rstest]
+    fn test_monotonic_data_does_not_overshoot() {
+        let curve = SplineCurve::new([(0.0, 0.0), (0.1, 0.9), (0.2, 1.0), (1.0, 1.0)]).unwrap();
+        let mut last = f32::NEG_INFINITY;
+        for i in 0..=1000 {
+            let y = curve.evaluate(i as f32 / 1000.0);
+            assert!(y >= last - 1e-6, "Curve is not monotonic");
+            assert!(y <= 1.0 + 1e-6, "Curve overshoots");
+            last = y;
+        }
+    }
+
+    #[rstest]
+    fn test_out_of_range_clamps() {
+        let curve = SplineCurve::new([(0.2, 0.5), (0.8, 0.1)]).unwrap();
+        assert_eq!(0.5, curve.evaluate(-1.0));
+        assert_eq!(0.1, curve.evaluate(2.0));
+        assert_eq!(0.0, SplineCurve::new([]).unwrap().evaluate(0.5));
+    }
+
+    #[rstest]
+    #[case(f32::NAN)]
+    #[case(f32::NEG_INFINITY)]
+    fn test_nan_and_low_positions_evaluate_to_first_point(#[case] x: f32) {
+        let curve = SplineCurve::new([(0.2, 0.5), (0.5, 0.9), (0.8, 0.1)]).unwrap();
+        assert_eq!(0.5, curve.evaluate(x));
+    }
+
+    #[rstest]
+    #[case(f32::NAN, 0.0)]
+    #[case(0.0, f32::NAN)]
+    #[case(f32::INFINITY, 0.0)]
+    #[case(0.0, f32::NEG_INFINITY)]
+    fn test_rejects_non_finite_breakpoints(#[case] x: f32, #[case] y: f32) {
+        assert!(matches!(
+            SplineCurve::new([(0.0, 0.0), (x, y)]),
+            Err(SplineCurveError::NonFinite(_))
+        ));
+
+        let mut curve = SplineCurve::identity();
+        assert!(curve.insert(x, y).is_err());
+        assert!(curve.move_point(1, x, y).is_err());
+        assert_eq!(SplineCurve::identity(), curve);
+
+        let mut bytes = SplineCurve::identity().to_bytes();
+        bytes[13..17].copy_from_slice(&x.to_le_bytes());
+        bytes[17..21].copy_from_slice(&y.to_le_bytes());
+        assert!(matches!(
+            SplineCurve::from_bytes(&bytes),
+            Err(SplineCurveError::NonFinite(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_insert_remove() {
+        let mut curve = SplineCurve::identity();
+        assert_eq!(1, curve.insert(0.5, 0.9).unwrap());
+        assert_relative_eq!(0.9, curve.evaluate(0.5));
+        assert_eq!(Breakpoint { x: 0.5, y: 0.9 }, curve.remove(1));
+        assert_eq!(SplineCurve::identity(), curve);
+    }
+
+    #[rstest]
+    fn test_serialization_roundtrip() {
+        let curve = SplineCurve::new([(0.0, 1.0), (0.3, 0.2), (1.0, 0.0)]).unwrap();
+        let bytes = curve.to_bytes();
+        assert_eq!(curve, SplineCurve::try_from(Value::Binary(&bytes)).unwrap());
+    }
+
+    #[rstest]
+    fn test_deserialization_errors() {
+        assert!(matches!(
+            SplineCurve::from_bytes(&[]),
+            Err(SplineCurveError::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            SplineCurve::from_bytes(&[2, 0, 0, 0, 0]),
+            Err(SplineCurveError::UnsupportedVersion(2))
+        ));
+        let mut bytes = SplineCurve::identity().to_bytes();
+        bytes.pop();
+        assert!(matches!(
+            SplineCurve::from_bytes(&bytes),
+            Err(SplineCurveError::InvalidLength {
+                expected: 21,
+                found: 20
+            })
+        ));
+        assert!(matches!(
+            SplineCurve::from_bytes(&[1, 0xff, 0xff, 0xff, 0xff]),
+            Err(SplineCurveError::InvalidLength { found: 5, .. })
+        ));
+        assert!(matches!(
+            SplineCurve::try_from(Value::Float(1.0)),
+            Err(SplineCurveError::Value(_))
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+//! Helpers to run audio-adjacent threads at realtime priority.
+//!
+//! Threads that feed or drive audio processing (standalone runners, background renderers, worker
+//! threads preparing data for the audio callback) benefit from being scheduled with realtime
+//! priority, so that they are not preempted by regular applications. Each platform exposes this
+//! differently:
+//!
+//! - On macOS and iOS, the thread is switched to the Mach time-constraint policy, using the
+//!   expected processing period,
+//! - On other Unix systems (Linux, BSDs), the thread is switched to the `SCHED_FIFO` policy,
+//! - On Windows, the thread is registered with the "Pro Audio" MMCSS task, falling back to the
+//!   time-critical thread priority.
+//!
+//! Promotion usually requires privileges (e.g. an `rtprio` limit on Linux), and can therefore
+//! fail at runtime. None of the functions here panic: [`promote_current_thread`] reports the
+//! failure, and [`spawn_realtime`] runs the thread at normal priority when promotion fails.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//! use clogbox_core::thread::spawn_realtime;
+//!
+//! // Worker thread processing blocks of 512 samples at 48 kHz
+//! let period = Duration::from_secs_f64(512.0 / 48000.0);
+//! let handle = spawn_realtime("render", period, |promoted| {
+//!     if let Err(err) = promoted {
+//!         eprintln!("Running at normal priority: {err}");
+//!     }
+//!     // Render audio here
+//!     42
+//! })
+//! .unwrap();
+//! assert_eq!(42, handle.join().unwrap());
+//! ```
+use std::io;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Error type for failed promotions of a thread to realtime priority.
+#[derive(Debug, Error)]
+pub enum RealtimePriorityError {
+    /// Realtime thread priority is not available on this platform.
+    #[error("Realtime thread priority is not supported on this platform")]
+    Unsupported,
+    /// The operating system refused to change the scheduling of the thread, usually because of
+    /// missing privileges.
+    #[error("Cannot promote thread to realtime priority: {0}")]
+    Os(#[from] io::Error),
+}
+
+/// Promotes the calling thread to realtime priority.
+///
+/// # Arguments
+///
+/// * `period` - Expected duration between two processing cycles of the thread, typically the
+///   duration of one block of audio. Only used on Apple platforms, where it informs the scheduler
+///   of the thread's time constraints.
+///
+/// # Returns
+///
+/// `Ok(())` if the thread now runs at realtime priority, or the reason why it could not be
+/// promoted. In the latter case, the thread keeps its previous priority and can continue running
+/// normally.
+pub fn promote_current_thread(period: Duration) -> Result<(), RealtimePriorityError> {
+    imp::promote_current_thread(period)
+}
+
+/// Spawns a named thread, and promotes it to realtime priority before running `f`.
+///
+/// The thread runs at normal priority if the promotion fails; the result of the promotion is
+/// given to `f` so that it can report the failure, or adapt its behavior.
+///
+/// # Arguments
+///
+/// * `name` - Name of the spawned thread.
+/// * `period` - Expected duration between two processing cycles of the thread, see
+///   [`promote_current_thread`].
+/// * `f` - Function to run in the spawned thread.
+///
+/// # Returns
+///
+/// The join handle of the spawned thread, or an error if the thread could not be spawned.
+pub fn spawn_realtime<F, T>(
+    name: impl Into<String>,
+    period: Duration,
+    f: F,
+) -> io::Result<JoinHandle<T>>
+where
+    F: FnOnce(Result<(), RealtimePriorityError>) -> T + Send + 'static,
+    T: Send + 'static,
+{
+    thread::Builder::new()
+        .name(name.into())
+        .spawn(move || f(promote_current_thread(period)))
+}
+
+#[cfg(target_vendor = "apple")]
+mod imp {
+    use super::RealtimePriorityError;
+    use std::io;
+    use std::time::Duration;
+
+    #[allow(deprecated)]
+    pub(super) fn promote_current_thread(period: Duration) -> Result<(), RealtimePriorityError> {
+        // SAFETY: the timebase is written by the kernel, and the policy is only applied to the
+        // calling thread
+        unsafe {
+            let mut timebase = libc::mach_timebase_info { numer: 0, denom: 0 };
+            if libc::mach_timebase_info(&mut timebase) != libc::KERN_SUCCESS || timebase.numer == 0
+            {
+                return Err(RealtimePriorityError::Unsupported);
+            }
+            // Convert the period into Mach absolute time units
+            let period = (period.as_nanos() * timebase.denom as u128 / timebase.numer as u128)
+                .min(u32::MAX as u128) as u32;
+            let mut policy = libc::thread_time_constraint_policy {
+                period,
+                computation: period / 2,
+                constraint: period,
+                preemptible: 1,
+            };
+            let thread = libc::pthread_mach_thread_np(libc::pthread_self());
+            match libc::thread_policy_set(
+                thread,
+                libc::THREAD_TIME_CONSTRAINT_POLICY as _,
+                &mut policy as *mut _ as libc::thread_policy_t,
+                libc::THREAD_TIME_CONSTRAINT_POLICY_COUNT,
+            ) {
+                libc::KERN_SUCCESS => Ok(()),
+                code => Err(io::Error::other(format!("thread_policy_set failed ({code})")).into()),
+            }
+        }
+    }
+}
+
+#[cfg(all(unix, not(target_vendor = "apple")))]
+mod imp {
+    use super::RealtimePriorityError;
+    use std::io;
+    use std::time::Duration;
+
+    pub(super) fn promote_current_thread(_period: Duration) -> Result<(), RealtimePriorityError> {
+        // SAFETY: the scheduling parameters are fully initialized, and only applied to the calling
+        // thread
+        unsafe {
+            let min = libc::sched_get_priority_min(libc::SCHED_FIFO);
+            let max = libc::sched_get_priority_max(libc::SCHED_FIFO);
+            if min < 0 || max < 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            let mut param: libc::sched_param = std::mem::zeroed();
+            // Middle of the range, which stays below the default rtprio limits of audio setups and
+            // leaves room for the audio server threads
+            param.sched_priority = min + (max - min) / 2;
+            match libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) {
+                0 => Ok(()),
+                code => Err(io::Error::from_raw_os_error(code).into()),
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use super::RealtimePriorityError;
+    use std::io;
+    use std::time::Duration;
+    use windows_sys::Win32::System::Threading::{
+        AvSetMmThreadCharacteristicsW, GetCurrentThread, SetThreadPriority,
+        THREAD_PRIORITY_TIME_CRITICAL,
+    };
+
+    pub(super) fn promote_current_thread(_period: Duration) -> Result<(), RealtimePriorityError> {
+        let task = Vec::from_iter("Pro Audio".encode_utf16().chain(Some(0)));
+        let mut task_index = 0;
+        // SAFETY: `task` is a null-terminated wide string outliving the call, and the priority is
+        // only applied to the calling thread
+        unsafe {
+            if AvSetMmThreadCharacteristicsW(task.as_ptr(), &mut task_index) != 0 {
+                return Ok(());
+            }
+            // MMCSS is unavailable (e.g. the service is disabled), fall back to thread priorities
+            if SetThreadPriority(GetCurrentThread(), THREAD_PRIORITY_TIME_CRITICAL) != 0 {
+                Ok(())
+            } else {
+                Err(io::Error::last_os_error().into())
+            }
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use super::RealtimePriorityError;
+    use std::time::Duration;
+
+    pub(super) fn promote_current_thread(_period: Duration) -> Result<(), RealtimePriorityError> {
+        Err(RealtimePriorityError::Unsupported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_realtime_runs_with_fallback() {
+        // Promotion depends on the privileges of the test runner; either way the thread must run
+        let handle = spawn_realtime("clogbox-test", Duration::from_millis(10), |_| {
+            thread::current().name().map(ToString::to_string)
+        })
+        .unwrap();
+        assert_eq!(Some("clogbox-test"), handle.join().unwrap().as_deref());
+    }
+}
@@ -113,18 +113,33 @@ impl DeriveEnum {
                 .unwrap();
             quote! { ::typenum::operator_aliases::Sum<#unit_count_ty, #variant_count_ty> }
         };
+        quote! {
+            #[automatically_derived]
+            impl ::clogbox_core::r#enum::Enum for #ident {
+                type Count = #count_ty;
+
+                fn name(&self) -> ::std::borrow::Cow<'_, str> {
+                    ::std::borrow::Cow::Borrowed(self.name_static())
+                }
+            }
+        }
+    }
+
+    fn impl_name_static(&self, ident: &syn::Ident, fields: &[EnumVariant]) -> TokenStream {
+        let is_unit = fields.iter().all(|variant| variant.fields.is_empty());
         let arms = fields.iter().map(|EnumVariant { ident, name, fields, prefix }| {
             let name = name
                 .clone()
                 .unwrap_or_else(|| ident.to_string());
             match fields.len() {
+                0 if is_unit => quote! { Self::#ident => #name },
                 0 => quote! { Self::#ident => ::std::borrow::Cow::from(#name) },
                 1 => {
                     let borrow = if let Some(prefix) = prefix {
                         let format_string = format!("{prefix} {{}}");
-                        quote! { ::std::borrow::Cow::Owned(format!(#format_string, inner.name())) }
+                        quote! { ::std::borrow::Cow::Owned(format!(#format_string, ::clogbox_core::r#enum::Enum::name(inner))) }
                     } else {
-                        quote! { inner.name() }
+                        quote! { ::clogbox_core::r#enum::Enum::name(inner) }
                     };
                     quote! {
                         Self::#ident(inner) => {
@@ -135,15 +150,40 @@ impl DeriveEnum {
                 _ => syn::Error::new(ident.span(), "Cannot derive Enum for enum with variants having more than 1 field").into_compile_error(),
             }
         });
+        // Unit-only enums have all their names known at compile time; composite enums need to
+        // format the names of their inner variants, which is done once and cached.
+        let body = if is_unit {
+            quote! {
+                match self {
+                    #(#arms),*
+                }
+            }
+        } else {
+            quote! {
+                static NAMES: ::std::sync::OnceLock<::std::boxed::Box<[::std::boxed::Box<str>]>> =
+                    ::std::sync::OnceLock::new();
+                let names = NAMES.get_or_init(|| {
+                    ::clogbox_core::r#enum::enum_iter::<Self>()
+                        .map(|this| {
+                            let name: ::std::borrow::Cow<str> = match &this {
+                                #(#arms),*
+                            };
+                            name.into_owned().into_boxed_str()
+                        })
+                        .collect()
+                });
+                &names[::clogbox_core::r#enum::az::Cast::<usize>::cast(*self)]
+            }
+        };
         quote! {
             #[automatically_derived]
-            impl ::clogbox_core::r#enum::Enum for #ident {
-                type Count = #count_ty;
-
-                fn name(&self) -> ::std::borrow::Cow<str> {
-                    match self {
-                        #(#arms),*
-                    }
+            impl #ident {
+                /// Returns the name of the enum variant as a static string.
+                ///
+                /// Names of composite variants are formatted once on first use, and cached for
+                /// the lifetime of the program.
+                pub fn name_static(&self) -> &'static str {
+                    #body
                 }
             }
         }
@@ -164,6 +204,7 @@ impl quote::ToTokens for DeriveEnum {
         tokens.extend(self.impl_cast_from(ident, fields));
         tokens.extend(self.impl_cast(ident, fields));
         tokens.extend(self.impl_enum(ident, fields));
+        tokens.extend(self.impl_name_static(ident, fields));
     }
 }
 
@@ -29,7 +29,7 @@ fn test_outer_has_correct_size() {
 
 #[test]
 fn test_inner_cast_from() {
-    let actual = [0, 1, 2, 3].map(|i| Inner::cast_from(i));
+    let actual = [0, 1, 2, 3].map(Inner::cast_from);
     let expected = [Inner::A, Inner::B, Inner::C, Inner::D];
     assert_eq!(expected, actual);
 }
@@ -40,3 +40,13 @@ fn test_outer_enum_iter() {
         .collect::<Vec<_>>();
     insta::assert_csv_snapshot!(expected);
 }
+
+#[test]
+fn test_outer_name_static() {
+    for e in enum_iter::<Outer>() {
+        assert_eq!(e.name(), e.name_static());
+    }
+    // Composite names are cached, and therefore always point to the same string
+    let name = Outer::Third(Inner::B).name_static();
+    assert!(std::ptr::eq(name, Outer::Third(Inner::B).name_static()));
+}
@@ -0,0 +1,411 @@
+//! Chorus and flanger modulation effects.
+//!
+//! This module provides [`Chorus`], a stereo modulated delay whose delay time is driven by a sine
+//! LFO. In [`ChorusMode::Chorus`] mode, the delay is long enough for the modulated copy to be
+//! perceived as a detuned double of the input, while in [`ChorusMode::Flanger`] mode the short
+//! delay and the feedback produce a sweeping comb filter.
+//!
+//! Depth, feedback and mix changes are smoothed per sample, so that they can be automated without
+//! zipper noise.
+//!
+//! The modulated delay is read with linear interpolation in real time, and with cubic
+//! interpolation when the stream is rendered offline ([`StreamData::is_offline`]).
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_core::param::SetParameter;
+//! use clogbox_effects::chorus::{Chorus, ChorusMode, ChorusParams};
+//!
+//! let stream_data = StreamData::new(44100.0, 120.0, 64);
+//! let mut chorus = Chorus::<f32>::new(stream_data.sample_rate);
+//! chorus.set_mode(ChorusMode::Flanger);
+//! chorus.set_param(ChorusParams::Feedback, 0.7f32);
+//!
+//! let (left, right) = ([0.5; 64], [0.5; 64]);
+//! let (mut out_left, mut out_right) = ([0.0; 64], [0.0; 64]);
+//! chorus.process(&stream_data, &[&left, &right], &mut [&mut out_left, &mut out_right]);
+//! ```
+use crate::delay::{feedback_tail, DelayBuffer, DelayInterpolation};
+use az::{Cast, CastFrom};
+use clogbox_core::module::stereo::Stereo;
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::param::smoothed::Smoothed;
+use clogbox_core::param::value::Value;
+use clogbox_core::param::{GetParameter, SetParameter};
+use clogbox_core::r#enum::{enum_iter, from_index_clamped};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_derive::Enum;
+use num_traits::Float;
+use numeric_literals::replace_float_literals;
+use std::f64::consts::TAU;
+
+/// Modulation effect produced by the [`Chorus`] module.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum ChorusMode {
+    /// Long modulated delay, thickening the signal.
+    #[default]
+    Chorus,
+    /// Short modulated delay with feedback, producing a sweeping comb filter.
+    Flanger,
+}
+
+impl ChorusMode {
+    /// Returns the minimum delay time and the maximum modulation depth, in seconds.
+    pub fn delay_range(&self) -> (f64, f64) {
+        match self {
+            Self::Chorus => (0.015, 0.010),
+            Self::Flanger => (0.001, 0.005),
+        }
+    }
+}
+
+/// Parameters of the [`Chorus`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum ChorusParams {
+    /// Modulation effect, as the index of a [`ChorusMode`] variant.
+    Mode,
+    /// LFO rate, in Hz.
+    Rate,
+    /// Modulation depth, in 0..1.
+    Depth,
+    /// Feedback gain, in -0.95..0.95.
+    Feedback,
+    /// Phase offset between the left and right LFOs, in 0..1 (0 to 180 degrees).
+    Spread,
+    /// Dry/wet mix, in 0..1.
+    Mix,
+}
+
+/// A stereo chorus and flanger module.
+#[derive(Debug, Clone)]
+pub struct Chorus<T> {
+    buffers: [DelayBuffer<T>; 2],
+    sample_rate: f64,
+    mode: ChorusMode,
+    rate: f32,
+    spread: f32,
+    phase: f64,
+    depth: Smoothed<T>,
+    feedback: Smoothed<T>,
+    mix: Smoothed<T>,
+}
+
+impl<T: Float + CastFrom<f64> + Cast<usize>> Chorus<T> {
+    /// Time taken by parameter changes to be fully applied, in seconds.
+    const SMOOTHING_TIME: f32 = 0.02;
+    /// Maximum feedback gain, keeping the flanger stable.
+    const MAX_FEEDBACK: f32 = 0.95;
+
+    /// Creates a new chorus for the given sample rate, with a 0.5 Hz LFO and a half wet mix.
+    pub fn new(sample_rate: f64) -> Self {
+        let smoothed =
+            |value| Smoothed::new(sample_rate as _, Self::SMOOTHING_TIME, T::cast_from(value));
+        Self {
+            buffers: [(); 2].map(|_| DelayBuffer::new(Self::buffer_capacity(sample_rate))),
+            sample_rate,
+            mode: ChorusMode::default(),
+            rate: 0.5,
+            spread: 0.5,
+            phase: 0.,
+            depth: smoothed(0.5),
+            feedback: smoothed(0.),
+            mix: smoothed(0.5),
+        }
+    }
+
+    /// Sets the modulation effect.
+    pub fn set_mode(&mut self, mode: ChorusMode) {
+        self.mode = mode;
+    }
+
+    /// Sets the LFO rate, in Hz.
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(0.);
+    }
+
+    /// Sets the modulation depth, clamped to 0..1.
+    pub fn set_depth(&mut self, depth: f32) {
+        self.depth
+            .set_target(T::cast_from(depth.clamp(0., 1.) as f64));
+    }
+
+    /// Sets the feedback gain, clamped to -0.95..0.95.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        let feedback = feedback.clamp(-Self::MAX_FEEDBACK, Self::MAX_FEEDBACK);
+        self.feedback.set_target(T::cast_from(feedback as f64));
+    }
+
+    /// Sets the phase offset between the left and right LFOs, clamped to 0..1.
+    pub fn set_spread(&mut self, spread: f32) {
+        self.spread = spread.clamp(0., 1.);
+    }
+
+    /// Sets the dry/wet mix, clamped to 0..1.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix.set_target(T::cast_from(mix.clamp(0., 1.) as f64));
+    }
+
+    /// Returns the phase offset of the right LFO, in turns.
+    fn spread_phase(&self) -> f64 {
+        self.spread as f64 * 0.5
+    }
+
+    fn buffer_capacity(sample_rate: f64) -> usize {
+        let (min, depth) = ChorusMode::Chorus.delay_range();
+        ((min + depth) * sample_rate).ceil() as usize + 3
+    }
+}
+
+impl<T: 'static + Send + Float + Cast<f32> + CastFrom<f64> + Cast<usize>> Module for Chorus<T> {
+    type Sample = T;
+    type Inputs = Stereo;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        if stream_data.sample_rate != self.sample_rate {
+            self.sample_rate = stream_data.sample_rate;
+            self.buffers =
+                [(); 2].map(|_| DelayBuffer::new(Self::buffer_capacity(self.sample_rate)));
+            for param in [&mut self.depth, &mut self.feedback, &mut self.mix] {
+                param.set_sample_rate(self.sample_rate as _);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        for buffer in &mut self.buffers {
+            buffer.clear();
+        }
+        self.phase = 0.;
+        for param in [&mut self.depth, &mut self.feedback, &mut self.mix] {
+            param.reset(param.target());
+        }
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        input_latencies
+    }
+
+    #[profiling::function]
+    #[replace_float_literals(T::cast_from(literal))]
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let interpolation = if stream_data.is_offline {
+            DelayInterpolation::Cubic
+        } else {
+            DelayInterpolation::Linear
+        };
+        let (min_delay, max_depth) = self.mode.delay_range();
+        let min_delay = T::cast_from(min_delay * self.sample_rate);
+        let max_depth = T::cast_from(max_depth * self.sample_rate);
+        let phase_step = self.rate as f64 / self.sample_rate;
+        let spread = self.spread_phase();
+        let block_size = inputs[0].len();
+
+        for i in 0..block_size {
+            let depth = self.depth.next_value() * max_depth;
+            let feedback = self.feedback.next_value();
+            let wet = self.mix.next_value();
+            let dry = 1.0 - wet;
+
+            for channel in enum_iter::<Stereo>() {
+                let phase = match channel {
+                    Stereo::Left => self.phase,
+                    Stereo::Right => self.phase + spread,
+                };
+                let lfo = 0.5 + 0.5 * T::cast_from((TAU * phase).sin());
+                let buffer = &mut self.buffers[channel.cast()];
+                let x = inputs[channel.cast()][i];
+                // The buffer is read before pushing the current sample, hence the offset
+                let delayed = buffer.tap_next(min_delay + depth * lfo - 1.0, x, interpolation);
+                buffer.push(x + feedback * delayed);
+                outputs[channel.cast()][i] = dry * x + wet * delayed;
+            }
+            self.phase = (self.phase + phase_step).fract();
+        }
+
+        let (min_delay, max_depth) = self.mode.delay_range();
+        let max_delay = (min_delay + max_depth) * self.sample_rate;
+        feedback_tail(max_delay, self.feedback.target().cast())
+    }
+}
+
+impl<T: Copy + Cast<f32>> GetParameter for Chorus<T> {
+    type Param = ChorusParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            ChorusParams::Mode => Value::Int(self.mode.cast() as i64),
+            ChorusParams::Rate => Value::Float(self.rate),
+            ChorusParams::Depth => Value::Float(self.depth.target().cast()),
+            ChorusParams::Feedback => Value::Float(self.feedback.target().cast()),
+            ChorusParams::Spread => Value::Float(self.spread),
+            ChorusParams::Mix => Value::Float(self.mix.target().cast()),
+        }
+    }
+}
+
+impl<T: Float + Cast<f32> + CastFrom<f64> + Cast<usize>> SetParameter for Chorus<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        match param {
+            ChorusParams::Mode => {
+                if let Ok(index) = i64::try_from(value) {
+                    self.mode = from_index_clamped(index);
+                }
+            }
+            ChorusParams::Rate => {
+                if let Ok(rate) = f32::try_from(value) {
+                    self.set_rate(rate);
+                }
+            }
+            ChorusParams::Depth => {
+                if let Ok(depth) = f32::try_from(value) {
+                    self.set_depth(depth);
+                }
+            }
+            ChorusParams::Feedback => {
+                if let Ok(feedback) = f32::try_from(value) {
+                    self.set_feedback(feedback);
+                }
+            }
+            ChorusParams::Spread => {
+                if let Ok(spread) = f32::try_from(value) {
+                    self.set_spread(spread);
+                }
+            }
+            ChorusParams::Mix => {
+                if let Ok(mix) = f32::try_from(value) {
+                    self.set_mix(mix);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    const STREAM_DATA: StreamData = StreamData::new(8000., 120., 256);
+
+    fn process(chorus: &mut Chorus<f64>, left: &[f64], right: &[f64]) -> [Vec<f64>; 2] {
+        process_stream(chorus, &STREAM_DATA, left, right)
+    }
+
+    fn process_stream(
+        chorus: &mut Chorus<f64>,
+        stream_data: &StreamData,
+        left: &[f64],
+        right: &[f64],
+    ) -> [Vec<f64>; 2] {
+        let mut outputs = [vec![0.; left.len()], vec![0.; right.len()]];
+        let [out_left, out_right] = &mut outputs;
+        chorus.process(stream_data, &[left, right], &mut [out_left, out_right]);
+        outputs
+    }
+
+    #[rstest]
+    fn test_dry_signal_passes_through() {
+        let mut chorus = Chorus::new(STREAM_DATA.sample_rate);
+        chorus.mix.reset(0.);
+        let input = Vec::from_iter((0..256).map(|i| (i as f64 * 0.1).sin()));
+        let [left, right] = process(&mut chorus, &input, &input);
+        assert_eq!(input, left);
+        assert_eq!(input, right);
+    }
+
+    #[rstest]
+    #[case(ChorusMode::Chorus, 120)]
+    #[case(ChorusMode::Flanger, 8)]
+    fn test_impulse_is_delayed(#[case] mode: ChorusMode, #[case] expected_onset: usize) {
+        let mut chorus = Chorus::new(STREAM_DATA.sample_rate);
+        chorus.set_mode(mode);
+        chorus.mix.reset(1.);
+        chorus.depth.reset(0.);
+        let mut input = vec![0.; 256];
+        input[0] = 1.;
+        let [left, _] = process(&mut chorus, &input, &input);
+        let onset = left.iter().position(|x| *x != 0.).unwrap();
+        assert_eq!(expected_onset, onset);
+        assert_relative_eq!(1., left[onset]);
+    }
+
+    #[rstest]
+    fn test_spread_decorrelates_channels() {
+        let mut chorus = Chorus::new(STREAM_DATA.sample_rate);
+        chorus.set_rate(5.);
+        chorus.mix.reset(1.);
+        let input = Vec::from_iter((0..256).map(|i| (i as f64 * 0.3).sin()));
+        let [left, right] = process(&mut chorus, &input, &input);
+        assert_ne!(left, right);
+
+        chorus.reset();
+        chorus.set_spread(0.);
+        let [left, right] = process(&mut chorus, &input, &input);
+        assert_eq!(left, right);
+    }
+
+    #[rstest]
+    fn test_params_are_smoothed() {
+        let mut chorus = Chorus::<f64>::new(STREAM_DATA.sample_rate);
+        chorus.set_param(ChorusParams::Mix, 1.0f32);
+        assert_eq!(Value::Float(1.), chorus.get_param_raw(ChorusParams::Mix));
+        assert_eq!(0.5, chorus.mix.current());
+        process(&mut chorus, &[0.; 256], &[0.; 256]);
+        assert_eq!(1., chorus.mix.current());
+    }
+
+    #[rstest]
+    fn test_f64_mix_ramp() {
+        let mut chorus = Chorus::<f64>::new(STREAM_DATA.sample_rate);
+        chorus.depth.reset(0.);
+        let mix = 0.3f32;
+        chorus.set_param(ChorusParams::Mix, mix);
+        // The delayed signal is silent for the first 120 samples, leaving only the dry signal
+        let [left, _] = process(&mut chorus, &[1.; 64], &[1.; 64]);
+        // Mix values smoothed in f32 would be off by about 1e-8
+        for (i, x) in left.into_iter().enumerate() {
+            let wet = 0.5 + (mix as f64 - 0.5) * (i + 1) as f64 / 160.;
+            assert_relative_eq!(1. - wet, x, epsilon = 1e-15);
+        }
+    }
+
+    #[rstest]
+    fn test_offline_uses_cubic_interpolation() {
+        // The LFO stays at its center, for a constant fractional delay of 120 + 0.33 * 40 samples
+        let delay = 133.2;
+        let input = Vec::from_iter((0..256).map(|i| (i as f64 * 0.1).sin()));
+        let error = |is_offline| {
+            let stream_data = StreamData {
+                is_offline,
+                ..STREAM_DATA
+            };
+            let mut chorus = Chorus::new(stream_data.sample_rate);
+            chorus.set_rate(0.);
+            chorus.mix.reset(1.);
+            chorus.depth.reset(0.33);
+            let [left, _] = process_stream(&mut chorus, &stream_data, &input, &input);
+            (140..256)
+                .map(|i| (left[i] - ((i as f64 - delay) * 0.1).sin()).abs())
+                .fold(0., f64::max)
+        };
+        let (realtime, offline) = (error(false), error(true));
+        assert!(realtime < 1e-3);
+        assert!(offline < realtime / 10.);
+    }
+}
@@ -0,0 +1,341 @@
+//! Uniformly-partitioned FFT convolution.
+//!
+//! This module provides [`ImpulseResponse`], an impulse response split into partitions of equal
+//! size and transformed into the frequency domain ahead of time, and [`Convolver`], a module
+//! convolving its input with the impulse response held by a [`Slot`].
+//!
+//! The convolution is computed with the overlap-save method, one partition at a time: each block
+//! of input is transformed once, kept in a frequency-domain delay line, and multiplied with every
+//! partition of the impulse response. The cost of the convolution therefore grows linearly with
+//! the length of the impulse response, while the latency only depends on the partition size.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_effects::convolution::{Convolver, ImpulseResponse};
+//! use clogbox_effects::slot::Slot;
+//!
+//! let stream_data = StreamData::new(44100.0, 120.0, 64);
+//! let slot = Slot::new();
+//! // Impulse responses are prepared outside the audio thread
+//! slot.store(ImpulseResponse::new(&[0.5f32, 0.25], 64));
+//!
+//! let mut convolver = Convolver::new(slot.clone(), 64, 44100);
+//! let mut input = [0.0; 64];
+//! input[0] = 1.0;
+//! let mut output = [0.0; 64];
+//! convolver.process(&stream_data, &[&input], &mut [&mut output]);
+//! assert_eq!(63, convolver.latency_samples());
+//! assert!((output[63] - 0.5).abs() < 1e-6);
+//! ```
+use crate::slot::Slot;
+use az::CastFrom;
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::Sequential;
+use num_complex::Complex;
+use num_traits::{Float, Zero};
+use rustfft::{Fft, FftNum, FftPlanner};
+use std::fmt;
+use std::sync::Arc;
+use typenum::U1;
+
+/// An impulse response, partitioned and transformed for use by a [`Convolver`].
+#[derive(Debug, Clone)]
+pub struct ImpulseResponse<T> {
+    partition_size: usize,
+    len: usize,
+    partitions: Box<[Box<[Complex<T>]>]>,
+}
+
+impl<T: FftNum + Float + CastFrom<f64>> ImpulseResponse<T> {
+    /// Prepares an impulse response for convolution with the given partition size. This allocates,
+    /// and must not be called from the audio thread.
+    ///
+    /// The partition size must match the one of the [`Convolver`] using the impulse response.
+    pub fn new(samples: &[T], partition_size: usize) -> Self {
+        let partition_size = partition_size.max(1);
+        let fft = FftPlanner::new().plan_fft_forward(2 * partition_size);
+        // The inverse transform is not normalized, so the normalization is folded into the
+        // impulse response
+        let scale = T::cast_from(0.5 / partition_size as f64);
+        let partitions = samples
+            .chunks(partition_size)
+            .map(|chunk| {
+                let mut spectrum = vec![Complex::zero(); 2 * partition_size];
+                for (bin, &x) in spectrum.iter_mut().zip(chunk) {
+                    *bin = Complex::new(x * scale, T::zero());
+                }
+                fft.process(&mut spectrum);
+                spectrum.truncate(partition_size + 1);
+                spectrum.into_boxed_slice()
+            })
+            .collect();
+        Self {
+            partition_size,
+            len: samples.len(),
+            partitions,
+        }
+    }
+}
+
+impl<T> ImpulseResponse<T> {
+    /// Returns the size of the partitions of the impulse response.
+    pub fn partition_size(&self) -> usize {
+        self.partition_size
+    }
+
+    /// Returns the length of the impulse response, in samples.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the impulse response is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// A module convolving its input with the impulse response held by a [`Slot`].
+///
+/// The delay line of the convolver is allocated up front for a maximum impulse response length;
+/// longer impulse responses are truncated. Impulse responses prepared with a partition size
+/// different from the one of the convolver are ignored, and produce silence.
+///
+/// Swapping impulse responses is real-time safe, but not crossfaded.
+pub struct Convolver<T: FftNum> {
+    slot: Slot<ImpulseResponse<T>>,
+    ir: Option<Arc<ImpulseResponse<T>>>,
+    partition_size: usize,
+    input: Box<[T]>,
+    output: Box<[T]>,
+    position: usize,
+    history: Box<[Box<[Complex<T>]>]>,
+    history_position: usize,
+    spectrum: Box<[Complex<T>]>,
+    scratch: Box<[Complex<T>]>,
+    forward: Arc<dyn Fft<T>>,
+    inverse: Arc<dyn Fft<T>>,
+}
+
+impl<T: FftNum> fmt::Debug for Convolver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Convolver")
+            .field("partition_size", &self.partition_size)
+            .field("max_partitions", &self.history.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: FftNum + Float> Convolver<T> {
+    /// Creates a new convolver.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - Slot holding the impulse response to convolve with.
+    /// * `partition_size` - Size of the partitions, which sets the latency of the convolver.
+    /// * `max_length` - Maximum length of the impulse response, in samples.
+    pub fn new(slot: Slot<ImpulseResponse<T>>, partition_size: usize, max_length: usize) -> Self {
+        let partition_size = partition_size.max(1);
+        let max_partitions = max_length.div_ceil(partition_size).max(1);
+        let mut planner = FftPlanner::new();
+        let forward = planner.plan_fft_forward(2 * partition_size);
+        let inverse = planner.plan_fft_inverse(2 * partition_size);
+        let scratch_len = forward
+            .get_inplace_scratch_len()
+            .max(inverse.get_inplace_scratch_len());
+        Self {
+            slot,
+            ir: None,
+            partition_size,
+            input: vec![T::zero(); 2 * partition_size].into_boxed_slice(),
+            output: vec![T::zero(); partition_size].into_boxed_slice(),
+            position: partition_size,
+            history: (0..max_partitions)
+                .map(|_| vec![Complex::zero(); partition_size + 1].into_boxed_slice())
+                .collect(),
+            history_position: 0,
+            spectrum: vec![Complex::zero(); 2 * partition_size].into_boxed_slice(),
+            scratch: vec![Complex::zero(); scratch_len].into_boxed_slice(),
+            forward,
+            inverse,
+        }
+    }
+
+    /// Returns the latency introduced by the convolver, in samples.
+    pub fn latency_samples(&self) -> usize {
+        self.partition_size - 1
+    }
+
+    fn process_block(&mut self) {
+        let b = self.partition_size;
+        for (bin, &x) in self.spectrum.iter_mut().zip(&*self.input) {
+            *bin = Complex::new(x, T::zero());
+        }
+        self.forward
+            .process_with_scratch(&mut self.spectrum, &mut self.scratch);
+        self.history[self.history_position].copy_from_slice(&self.spectrum[..=b]);
+
+        let ir = self
+            .ir
+            .as_deref()
+            .filter(|ir| ir.partition_size == self.partition_size);
+        self.spectrum.fill(Complex::zero());
+        if let Some(ir) = ir {
+            let count = self.history.len();
+            for (age, partition) in ir.partitions.iter().take(count).enumerate() {
+                let block = &self.history[(self.history_position + count - age) % count];
+                for ((acc, &x), &h) in self.spectrum.iter_mut().zip(&**block).zip(&**partition) {
+                    *acc = *acc + x * h;
+                }
+            }
+        }
+        for k in 1..b {
+            self.spectrum[2 * b - k] = self.spectrum[k].conj();
+        }
+        self.inverse
+            .process_with_scratch(&mut self.spectrum, &mut self.scratch);
+
+        // Overlap-save: only the second half of the block is free of circular aliasing
+        for (out, bin) in self.output.iter_mut().zip(&self.spectrum[b..]) {
+            *out = bin.re;
+        }
+        self.input.copy_within(b.., 0);
+        self.history_position = (self.history_position + 1) % self.history.len();
+    }
+}
+
+impl<T: 'static + Send + Sync + FftNum + Float> Module for Convolver<T> {
+    type Sample = T;
+    type Inputs = Sequential<U1>;
+    type Outputs = Sequential<U1>;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.input.fill(T::zero());
+        self.output.fill(T::zero());
+        for block in self.history.iter_mut() {
+            block.fill(Complex::zero());
+        }
+        self.position = self.partition_size;
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let latency = self.latency_samples() as f64;
+        EnumMapArray::new(|i| input_latencies[i] + latency)
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        self.slot.fetch(&mut self.ir);
+        let b = self.partition_size;
+        for (out, &x) in outputs[0].iter_mut().zip(inputs[0]) {
+            self.input[self.position] = x;
+            self.position += 1;
+            if self.position == 2 * b {
+                self.position = b;
+                self.process_block();
+            }
+            *out = self.output[self.position - b];
+        }
+        // Only the part of the impulse response fitting in the delay line is applied
+        let max_len = self.history.len() * self.partition_size;
+        let ir_len = self
+            .ir
+            .as_deref()
+            .filter(|ir| ir.partition_size == self.partition_size)
+            .map_or(0, |ir| ir.len().min(max_len));
+        ProcessStatus::Tail((ir_len + self.latency_samples()) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    const STREAM_DATA: StreamData = StreamData::new(1000., 120., 100);
+
+    fn noise(len: usize, seed: u32) -> Vec<f64> {
+        let mut state = seed;
+        Vec::from_iter((0..len).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f64 / u32::MAX as f64 * 2. - 1.
+        }))
+    }
+
+    fn convolve(convolver: &mut Convolver<f64>, input: &[f64]) -> Vec<f64> {
+        let mut output = vec![0.; input.len()];
+        for (input, output) in input.chunks(23).zip(output.chunks_mut(23)) {
+            convolver.process(&STREAM_DATA, &[input], &mut [output]);
+        }
+        output
+    }
+
+    #[rstest]
+    #[case(16, 100)]
+    #[case(32, 32)]
+    #[case(7, 50)]
+    fn test_matches_direct_convolution(#[case] partition_size: usize, #[case] ir_len: usize) {
+        let ir = noise(ir_len, 42);
+        let input = noise(500, 1234);
+        let slot = Slot::new();
+        slot.store(ImpulseResponse::new(&ir, partition_size));
+        let mut convolver = Convolver::new(slot, partition_size, ir_len);
+        let output = convolve(&mut convolver, &input);
+
+        let latency = convolver.latency_samples();
+        for (n, actual) in output.iter().enumerate().skip(latency) {
+            let n = n - latency;
+            let expected: f64 = (0..=n.min(ir_len - 1)).map(|k| ir[k] * input[n - k]).sum();
+            assert_relative_eq!(expected, *actual, epsilon = 1e-9);
+        }
+    }
+
+    #[rstest]
+    fn test_truncates_long_impulse_responses() {
+        let slot = Slot::new();
+        slot.store(ImpulseResponse::new(&[1., 0., 0., 0., 1.], 4));
+        let mut convolver = Convolver::new(slot, 4, 4);
+        let mut input = vec![0.; 12];
+        input[0] = 1.;
+        let output = convolve(&mut convolver, &input);
+        assert_relative_eq!(1., output[3], epsilon = 1e-12);
+        assert!(output[4..].iter().all(|x| x.abs() < 1e-12));
+        let status = convolver.process(&STREAM_DATA, &[&[0.; 4]], &mut [&mut [0.; 4]]);
+        assert_eq!(ProcessStatus::Tail(4 + 3), status);
+    }
+
+    #[rstest]
+    fn test_swap_impulse_response() {
+        let slot = Slot::new();
+        slot.store(ImpulseResponse::new(&[1.], 8));
+        let mut convolver = Convolver::new(slot.clone(), 8, 8);
+        let output = convolve(&mut convolver, &[1.; 16]);
+        assert_relative_eq!(1., output[15], epsilon = 1e-12);
+
+        slot.store(ImpulseResponse::new(&[0.5], 8));
+        let output = convolve(&mut convolver, &[1.; 16]);
+        assert_relative_eq!(0.5, output[15], epsilon = 1e-12);
+
+        // Mismatched partition sizes are ignored
+        slot.store(ImpulseResponse::new(&[1.], 4));
+        let output = convolve(&mut convolver, &[1.; 16]);
+        assert!(output[8..].iter().all(|x| x.abs() < 1e-12));
+    }
+}
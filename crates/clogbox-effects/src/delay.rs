@@ -0,0 +1,513 @@
+//! Fractional delay lines.
+//!
+//! This module provides [`DelayBuffer`], a circular buffer which can be read at fractional delays
+//! with any [`Interpolation`] method, and [`DelayLine`], a feedback delay module built on top of
+//! it. The buffer is meant to be reused as a building block for modulated effects such as chorus
+//! or reverb.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_effects::delay::DelayLine;
+//!
+//! let stream_data = StreamData::new(32.0, 120.0, 8);
+//! let mut delay = DelayLine::<f32>::new(stream_data.sample_rate, 1.0);
+//! delay.set_time(0.125);
+//! delay.set_mix(1.0);
+//!
+//! let mut input = [0.0; 8];
+//! input[0] = 1.0;
+//! let mut output = [0.0; 8];
+//! delay.process(&stream_data, &[&input], &mut [&mut output]);
+//! assert_eq!(1.0, output[4]);
+//! ```
+use az::{Cast, CastFrom};
+use clogbox_core::math::interpolation::{Cubic, Interpolation, Linear};
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::param::value::Value;
+use clogbox_core::param::{GetParameter, SetParameter};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::{from_index_clamped, Sequential};
+use clogbox_derive::Enum;
+use num_traits::{Float, Zero};
+use numeric_literals::replace_float_literals;
+use typenum::U1;
+
+/// Interpolation method used when reading a delay buffer at fractional delays.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum DelayInterpolation {
+    /// Rounds the delay to the nearest whole sample.
+    Nearest,
+    /// Linear interpolation between the two nearest samples.
+    #[default]
+    Linear,
+    /// Cubic interpolation between the four nearest samples.
+    Cubic,
+}
+
+/// A circular buffer holding the past samples of a signal.
+///
+/// Delays are expressed in samples, where a delay of 0 reads the most recently pushed sample.
+#[derive(Debug, Clone)]
+pub struct DelayBuffer<T> {
+    data: Box<[T]>,
+    pos: usize,
+}
+
+impl<T: Copy + Zero> DelayBuffer<T> {
+    /// Creates a new, silent delay buffer holding `capacity` samples (at least 1).
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            data: vec![T::zero(); capacity.max(1)].into_boxed_slice(),
+            pos: 0,
+        }
+    }
+
+    /// Returns the number of samples held by this buffer.
+    pub fn capacity(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Fills the buffer with silence.
+    pub fn clear(&mut self) {
+        self.data.fill(T::zero());
+    }
+
+    /// Pushes a new sample into the buffer, discarding the oldest one.
+    #[inline]
+    pub fn push(&mut self, value: T) {
+        self.pos = (self.pos + 1) % self.data.len();
+        self.data[self.pos] = value;
+    }
+
+    /// Returns the sample pushed `delay` samples ago. Delays larger than the buffer capacity
+    /// return the oldest sample.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_effects::delay::DelayBuffer;
+    /// let mut buffer = DelayBuffer::new(4);
+    /// for i in 0..6 {
+    ///     buffer.push(i as f32);
+    /// }
+    /// assert_eq!(5.0, buffer.get(0));
+    /// assert_eq!(3.0, buffer.get(2));
+    /// assert_eq!(2.0, buffer.get(10));
+    /// ```
+    #[inline]
+    pub fn get(&self, delay: usize) -> T {
+        let len = self.data.len();
+        let delay = delay.min(len - 1);
+        self.data[(self.pos + len - delay) % len]
+    }
+}
+
+impl<T: Float + CastFrom<f64> + Cast<usize>> DelayBuffer<T> {
+    /// Reads the buffer at a fractional delay, using the given interpolation method.
+    ///
+    /// # Arguments
+    ///
+    /// * `delay` - Delay in samples, clamped to the capacity of the buffer.
+    /// * `interpolation` - Interpolation method used between samples.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_effects::delay::{DelayBuffer, DelayInterpolation};
+    /// let mut buffer = DelayBuffer::new(8);
+    /// buffer.push(1.0);
+    /// buffer.push(0.0);
+    /// assert_eq!(0.25, buffer.tap(0.25, DelayInterpolation::Linear));
+    /// assert_eq!(0.0, buffer.tap(0.25, DelayInterpolation::Nearest));
+    /// ```
+    #[inline]
+    pub fn tap(&self, delay: T, interpolation: DelayInterpolation) -> T {
+        self.tap_next(delay, self.get(0), interpolation)
+    }
+
+    /// Reads the buffer at a fractional delay, given the sample that is pushed next.
+    ///
+    /// Cubic interpolation needs a sample newer than the delay, which is not in the buffer yet for
+    /// delays below 1 sample. [`Self::tap`] repeats the most recently pushed sample instead, which
+    /// biases the read; this uses `next` as the newest sample. This is useful when reading the
+    /// buffer before pushing the current sample into it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_effects::delay::{DelayBuffer, DelayInterpolation};
+    /// let mut buffer = DelayBuffer::new(8);
+    /// for x in [3.0, 2.0, 1.0] {
+    ///     buffer.push(x);
+    /// }
+    /// assert_eq!(1.5, buffer.tap_next(0.5, 0.0, DelayInterpolation::Cubic));
+    /// ```
+    #[inline]
+    pub fn tap_next(&self, delay: T, next: T, interpolation: DelayInterpolation) -> T {
+        match interpolation {
+            DelayInterpolation::Nearest => self.get(delay.max(T::zero()).round().cast()),
+            DelayInterpolation::Linear => self.tap_with_next(delay, next, &Linear),
+            DelayInterpolation::Cubic => self.tap_with_next(delay, next, &Cubic),
+        }
+    }
+
+    /// Reads the buffer at a fractional delay, using any [`Interpolation`] implementation over the
+    /// four samples surrounding the delay.
+    pub fn tap_with(&self, delay: T, interpolation: &impl Interpolation<T>) -> T {
+        self.tap_with_next(delay, self.get(0), interpolation)
+    }
+
+    /// Reads the buffer at a fractional delay like [`Self::tap_with`], given the sample that is
+    /// pushed next (see [`Self::tap_next`]).
+    #[replace_float_literals(T::cast_from(literal))]
+    pub fn tap_with_next(&self, delay: T, next: T, interpolation: &impl Interpolation<T>) -> T {
+        let delay = delay.max(0.0);
+        let n: usize = delay.floor().cast();
+        // Samples are laid out from oldest to newest, so that the requested delay falls between
+        // the second and third values.
+        let values: &[T] = &[
+            self.get(n + 2),
+            self.get(n + 1),
+            self.get(n),
+            if n == 0 { next } else { self.get(n - 1) },
+        ];
+        interpolation.interpolate(&values, 2.0 - delay.fract())
+    }
+}
+
+/// Computes the tail of a feedback delay, that is the time for its echoes to decay by 60 dB.
+///
+/// Returns [`ProcessStatus::Running`] when the feedback does not decay.
+pub(crate) fn feedback_tail(delay: f64, feedback: f32) -> ProcessStatus {
+    const SILENCE_THRESHOLD: f64 = 1e-3;
+    let feedback = feedback.abs() as f64;
+    if feedback >= 1. {
+        return ProcessStatus::Running;
+    }
+    let repeats = if feedback <= SILENCE_THRESHOLD {
+        1.
+    } else {
+        (SILENCE_THRESHOLD.ln() / feedback.ln()).ceil() + 1.
+    };
+    ProcessStatus::Tail((delay * repeats).ceil() as u64)
+}
+
+/// Parameters of the [`DelayLine`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum DelayParams {
+    /// Delay time, in seconds, or in beats when synced to the tempo.
+    Time,
+    /// Whether the delay time is synced to the tempo (0 or 1).
+    Sync,
+    /// Feedback gain, in -1..1.
+    Feedback,
+    /// Dry/wet mix, in 0..1.
+    Mix,
+    /// Interpolation method, as the index of a [`DelayInterpolation`] variant.
+    Interpolation,
+}
+
+/// A mono delay module with feedback and dry/wet mix.
+///
+/// The delay time can either be expressed in seconds, or in beats, in which case it follows the
+/// tempo of the stream. Changes in delay time are ramped over the processed block, which avoids
+/// clicks and gives the usual pitch-bending effect of tape delays.
+#[derive(Debug, Clone)]
+pub struct DelayLine<T> {
+    buffer: DelayBuffer<T>,
+    sample_rate: f64,
+    max_time: f64,
+    time: f32,
+    sync: bool,
+    feedback: f32,
+    mix: f32,
+    interpolation: DelayInterpolation,
+    current_delay: Option<f64>,
+}
+
+impl<T: Copy + Zero> DelayLine<T> {
+    /// Creates a new delay line, with a fully wet 500 ms delay and no feedback.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Sample rate of the stream.
+    /// * `max_time` - Maximum delay time, in seconds. Longer delay times are clamped to it.
+    pub fn new(sample_rate: f64, max_time: f64) -> Self {
+        Self {
+            buffer: DelayBuffer::new(Self::buffer_capacity(sample_rate, max_time)),
+            sample_rate,
+            max_time,
+            time: 0.5,
+            sync: false,
+            feedback: 0.,
+            mix: 1.,
+            interpolation: DelayInterpolation::default(),
+            current_delay: None,
+        }
+    }
+
+    /// Sets the delay time in seconds, disabling tempo sync.
+    pub fn set_time(&mut self, seconds: f32) {
+        self.time = seconds.max(0.);
+        self.sync = false;
+    }
+
+    /// Sets the delay time in beats, enabling tempo sync.
+    pub fn set_time_beats(&mut self, beats: f32) {
+        self.time = beats.max(0.);
+        self.sync = true;
+    }
+
+    /// Sets the feedback gain, clamped to -1..1.
+    pub fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-1., 1.);
+    }
+
+    /// Sets the dry/wet mix, clamped to 0..1.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0., 1.);
+    }
+
+    /// Sets the interpolation method used to read the delay buffer.
+    pub fn set_interpolation(&mut self, interpolation: DelayInterpolation) {
+        self.interpolation = interpolation;
+    }
+
+    /// Computes the delay time in samples for the given stream, clamped between 1 sample and the
+    /// maximum delay time.
+    pub fn delay_samples(&self, stream_data: &StreamData) -> f64 {
+        let samples = if self.sync {
+            stream_data.beat_sample_length(self.time as f64)
+        } else {
+            self.time as f64 * stream_data.sample_rate
+        };
+        samples.clamp(1., (self.max_time * stream_data.sample_rate).max(1.))
+    }
+
+    fn buffer_capacity(sample_rate: f64, max_time: f64) -> usize {
+        // Extra samples for the interpolation taps around the maximum delay
+        (max_time * sample_rate).max(1.).ceil() as usize + 3
+    }
+}
+
+impl<T: 'static + Send + Float + CastFrom<f64> + Cast<usize>> Module for DelayLine<T> {
+    type Sample = T;
+    type Inputs = Sequential<U1>;
+    type Outputs = Sequential<U1>;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        if stream_data.sample_rate != self.sample_rate {
+            self.sample_rate = stream_data.sample_rate;
+            self.buffer = DelayBuffer::new(Self::buffer_capacity(self.sample_rate, self.max_time));
+            self.current_delay = None;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.clear();
+        self.current_delay = None;
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        input_latencies
+    }
+
+    #[profiling::function]
+    #[replace_float_literals(T::cast_from(literal))]
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let target = self.delay_samples(stream_data);
+        let mut delay = self.current_delay.unwrap_or(target);
+        let step = (target - delay) / inputs[0].len().max(1) as f64;
+        let feedback = T::cast_from(self.feedback as f64);
+        let wet = T::cast_from(self.mix as f64);
+        let dry = 1.0 - wet;
+
+        for (out, &x) in outputs[0].iter_mut().zip(inputs[0]) {
+            delay += step;
+            // The buffer is read before pushing the current sample, hence the offset. The feedback
+            // part of the current sample depends on this read, so only the input is used as the
+            // newest interpolation point.
+            let delayed = self
+                .buffer
+                .tap_next(T::cast_from(delay) - 1.0, x, self.interpolation);
+            self.buffer.push(x + feedback * delayed);
+            *out = dry * x + wet * delayed;
+        }
+        self.current_delay = Some(target);
+        feedback_tail(target, self.feedback)
+    }
+}
+
+impl<T> GetParameter for DelayLine<T> {
+    type Param = DelayParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            DelayParams::Time => Value::Float(self.time),
+            DelayParams::Sync => Value::Int(self.sync as i64),
+            DelayParams::Feedback => Value::Float(self.feedback),
+            DelayParams::Mix => Value::Float(self.mix),
+            DelayParams::Interpolation => Value::Int(self.interpolation.cast() as i64),
+        }
+    }
+}
+
+impl<T: Copy + Zero> SetParameter for DelayLine<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        match param {
+            DelayParams::Time => {
+                if let Ok(time) = f32::try_from(value) {
+                    self.time = time.max(0.);
+                }
+            }
+            DelayParams::Sync => {
+                if let Ok(sync) = i64::try_from(value) {
+                    self.sync = sync != 0;
+                }
+            }
+            DelayParams::Feedback => {
+                if let Ok(feedback) = f32::try_from(value) {
+                    self.set_feedback(feedback);
+                }
+            }
+            DelayParams::Mix => {
+                if let Ok(mix) = f32::try_from(value) {
+                    self.set_mix(mix);
+                }
+            }
+            DelayParams::Interpolation => {
+                if let Ok(index) = i64::try_from(value) {
+                    self.interpolation = from_index_clamped(index);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    const STREAM_DATA: StreamData = StreamData::new(1000., 120., 16);
+
+    fn impulse_response(delay: &mut DelayLine<f64>) -> [f64; 16] {
+        let mut input = [0.; 16];
+        input[0] = 1.;
+        let mut output = [0.; 16];
+        delay.process(&STREAM_DATA, &[&input], &mut [&mut output]);
+        output
+    }
+
+    #[rstest]
+    fn test_buffer_wraps_around() {
+        let mut buffer = DelayBuffer::new(3);
+        for i in 0..5 {
+            buffer.push(i as f64);
+        }
+        assert_eq!([4., 3., 2.], [buffer.get(0), buffer.get(1), buffer.get(2)]);
+    }
+
+    #[rstest]
+    #[case(DelayInterpolation::Nearest, 2.)]
+    #[case(DelayInterpolation::Linear, 1.5)]
+    #[case(DelayInterpolation::Cubic, 1.5)]
+    fn test_buffer_fractional_tap(
+        #[case] interpolation: DelayInterpolation,
+        #[case] expected: f64,
+    ) {
+        let mut buffer = DelayBuffer::new(8);
+        for x in [3., 2., 1., 0.] {
+            buffer.push(x);
+        }
+        assert_relative_eq!(expected, buffer.tap(1.5, interpolation));
+    }
+
+    #[rstest]
+    #[case(0.25, 0.25)]
+    #[case(0.5, 0.5)]
+    #[case(0.75, 0.75)]
+    fn test_buffer_cubic_tap_below_one_sample(#[case] delay: f64, #[case] expected: f64) {
+        let mut buffer = DelayBuffer::new(8);
+        for x in [3., 2., 1., 0.] {
+            buffer.push(x);
+        }
+        // A linear ramp is reproduced exactly by cubic interpolation
+        assert_relative_eq!(
+            expected,
+            buffer.tap_next(delay, -1., DelayInterpolation::Cubic),
+            epsilon = 1e-12
+        );
+        // Repeating the most recently pushed sample bends the ramp
+        assert!((buffer.tap(delay, DelayInterpolation::Cubic) - expected).abs() > 1e-3);
+    }
+
+    #[rstest]
+    fn test_echoes_with_feedback() {
+        let mut delay = DelayLine::new(STREAM_DATA.sample_rate, 1.);
+        delay.set_time(0.005);
+        delay.set_feedback(0.5);
+        let output = impulse_response(&mut delay);
+        assert_relative_eq!(1., output[5], epsilon = 1e-6);
+        assert_relative_eq!(0.5, output[10], epsilon = 1e-6);
+        assert_relative_eq!(0.25, output[15], epsilon = 1e-6);
+        assert_eq!(3, output.iter().filter(|x| x.abs() > 1e-6).count());
+    }
+
+    #[rstest]
+    fn test_dry_wet_mix() {
+        let mut delay = DelayLine::new(STREAM_DATA.sample_rate, 1.);
+        delay.set_time(0.004);
+        delay.set_mix(0.25);
+        let output = impulse_response(&mut delay);
+        assert_relative_eq!(0.75, output[0], epsilon = 1e-6);
+        assert_relative_eq!(0.25, output[4], epsilon = 1e-6);
+    }
+
+    #[rstest]
+    fn test_tempo_sync() {
+        let mut delay = DelayLine::<f64>::new(STREAM_DATA.sample_rate, 1.);
+        delay.set_time_beats(0.5);
+        assert_eq!(250., delay.delay_samples(&STREAM_DATA));
+        delay.set_param(DelayParams::Sync, 0);
+        assert_eq!(500., delay.delay_samples(&STREAM_DATA));
+    }
+
+    #[rstest]
+    #[case(0., ProcessStatus::Tail(5))]
+    #[case(0.5, ProcessStatus::Tail(55))]
+    #[case(1., ProcessStatus::Running)]
+    fn test_tail(#[case] feedback: f32, #[case] expected: ProcessStatus) {
+        let mut delay = DelayLine::new(STREAM_DATA.sample_rate, 1.);
+        delay.set_time(0.005);
+        delay.set_feedback(feedback);
+        let input = [0.; 16];
+        let mut output = [0.; 16];
+        let status = delay.process(&STREAM_DATA, &[&input], &mut [&mut output]);
+        assert_eq!(expected, status);
+    }
+
+    #[rstest]
+    fn test_params_roundtrip() {
+        let mut delay = DelayLine::<f32>::new(STREAM_DATA.sample_rate, 1.);
+        delay.set_param(DelayParams::Interpolation, 2);
+        delay.set_param(DelayParams::Feedback, 2.0f32);
+        assert_eq!(DelayInterpolation::Cubic, delay.interpolation);
+        assert_eq!(Value::Float(1.), delay.get_param_raw(DelayParams::Feedback));
+    }
+}
@@ -0,0 +1,1399 @@
+//! Dynamics processors.
+//!
+//! This module provides [`EnvelopeFollower`], an attack/release envelope detector,
+//! [`LevelDetector`], which measures the peak or RMS level of a signal, [`Compressor`], a
+//! stereo-linked feed-forward compressor with a soft knee and an optional sidechain input,
+//! [`Gate`], a noise gate sharing the same level detection, and [`Limiter`], a brickwall limiter
+//! with lookahead.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_effects::dynamics::Compressor;
+//!
+//! let stream_data = StreamData::new(44100.0, 120.0, 64);
+//! let mut compressor = Compressor::<f32>::new(stream_data.sample_rate);
+//! compressor.set_threshold(-24.0);
+//! compressor.set_ratio(4.0);
+//!
+//! let (left, right) = ([0.5; 64], [0.5; 64]);
+//! let sidechain = [0.0; 64];
+//! let (mut out_left, mut out_right) = ([0.0; 64], [0.0; 64]);
+//! compressor.process(
+//!     &stream_data,
+//!     &[&left, &right, &sidechain, &sidechain],
+//!     &mut [&mut out_left, &mut out_right],
+//! );
+//! assert!(out_left[63] < 0.5);
+//! ```
+use crate::delay::DelayBuffer;
+use az::{Cast, CastFrom};
+use clogbox_core::math::dsp::{db_to_linear, linear_to_db};
+use clogbox_core::math::interpolation::{Cubic, Interpolation};
+use clogbox_core::module::stereo::Stereo;
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::param::value::Value;
+use clogbox_core::param::{GetParameter, SetParameter};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::{enum_iter, from_index_clamped};
+use clogbox_derive::Enum;
+use num_traits::Float;
+use numeric_literals::replace_float_literals;
+
+/// Computes the coefficient of a one-pole filter reaching 63% of its target in `time` seconds.
+fn time_to_coefficient(sample_rate: f64, time: f64) -> f64 {
+    if time <= 0. {
+        0.
+    } else {
+        (-(time * sample_rate).recip()).exp()
+    }
+}
+
+/// An envelope detector, following its input with separate attack and release times.
+///
+/// The follower uses the attack time when its input rises above the current envelope, and the
+/// release time when it falls below it. Inputs are used as-is, which means that callers are
+/// responsible for rectifying audio signals beforehand.
+///
+/// # Example
+///
+/// ```rust
+/// use clogbox_effects::dynamics::EnvelopeFollower;
+/// let mut follower = EnvelopeFollower::<f32>::new(1000.0, 0.0, 0.01);
+/// assert_eq!(1.0, follower.process(1.0));
+/// assert!(follower.process(0.0) > 0.9);
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct EnvelopeFollower<T> {
+    state: T,
+    attack: T,
+    release: T,
+}
+
+impl<T: Float + CastFrom<f64>> EnvelopeFollower<T> {
+    /// Creates a new envelope follower.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Sample rate of the followed signal.
+    /// * `attack` - Attack time, in seconds.
+    /// * `release` - Release time, in seconds.
+    pub fn new(sample_rate: f64, attack: f64, release: f64) -> Self {
+        let mut this = Self {
+            state: T::zero(),
+            attack: T::zero(),
+            release: T::zero(),
+        };
+        this.set_times(sample_rate, attack, release);
+        this
+    }
+
+    /// Sets the attack and release times, in seconds.
+    pub fn set_times(&mut self, sample_rate: f64, attack: f64, release: f64) {
+        self.attack = T::cast_from(time_to_coefficient(sample_rate, attack));
+        self.release = T::cast_from(time_to_coefficient(sample_rate, release));
+    }
+
+    /// Resets the envelope to zero.
+    pub fn reset(&mut self) {
+        self.state = T::zero();
+    }
+
+    /// Returns the current value of the envelope.
+    pub fn value(&self) -> T {
+        self.state
+    }
+
+    /// Advances the follower by one sample, returning the new value of the envelope.
+    #[inline]
+    pub fn process(&mut self, x: T) -> T {
+        let coefficient = if x > self.state {
+            self.attack
+        } else {
+            self.release
+        };
+        self.state = x + coefficient * (self.state - x);
+        self.state
+    }
+}
+
+/// Level detection modes of a [`LevelDetector`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum DetectorMode {
+    /// Instantaneous absolute value of the signal.
+    #[default]
+    Peak,
+    /// Root mean square of the signal, averaged by a one-pole filter over the RMS window.
+    #[display = "RMS"]
+    Rms,
+    /// Root mean square of the signal, averaged exactly over a sliding window of the RMS window
+    /// length.
+    #[display = "True RMS"]
+    TrueRms,
+}
+
+/// Maximum length of the averaging window of a [`LevelDetector`], in seconds.
+pub const MAX_RMS_WINDOW: f64 = 0.3;
+
+/// A level detector, measuring the level of a signal with one of the [`DetectorMode`]s before
+/// smoothing it with an [`EnvelopeFollower`].
+///
+/// The detected level is linear by default, and can be output in decibels instead, which is how
+/// the [`Compressor`] and [`Gate`] modules use it.
+///
+/// # Example
+///
+/// ```rust
+/// use clogbox_effects::dynamics::{DetectorMode, LevelDetector};
+/// let mut detector = LevelDetector::<f64>::new(1000.0, DetectorMode::TrueRms);
+/// detector.set_window(0.004);
+/// let mut level = 0.0;
+/// for x in [1.0, -1.0, 1.0, -1.0] {
+///     level = detector.process(x);
+/// }
+/// assert_eq!(1.0, level);
+/// ```
+#[derive(Debug, Clone)]
+pub struct LevelDetector<T> {
+    mode: DetectorMode,
+    sample_rate: f64,
+    window: f64,
+    log_output: bool,
+    mean_square: T,
+    rms_coefficient: T,
+    history: Box<[T]>,
+    history_len: usize,
+    history_pos: usize,
+    history_sum: T,
+    envelope: EnvelopeFollower<T>,
+}
+
+impl<T> LevelDetector<T> {
+    /// Returns the detection mode.
+    pub fn mode(&self) -> DetectorMode {
+        self.mode
+    }
+}
+
+impl<T: Float + CastFrom<f64>> LevelDetector<T> {
+    /// Creates a new level detector, with a 10 ms RMS window, linear output and no smoothing.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Sample rate of the detected signal.
+    /// * `mode` - Detection mode.
+    pub fn new(sample_rate: f64, mode: DetectorMode) -> Self {
+        let mut this = Self {
+            mode,
+            sample_rate,
+            window: 0.01,
+            log_output: false,
+            mean_square: T::zero(),
+            rms_coefficient: T::zero(),
+            history: Box::new([]),
+            history_len: 1,
+            history_pos: 0,
+            history_sum: T::zero(),
+            envelope: EnvelopeFollower::new(sample_rate, 0., 0.),
+        };
+        this.set_sample_rate(sample_rate);
+        this
+    }
+
+    /// Sets the detection mode, resetting the detector when it changes.
+    pub fn set_mode(&mut self, mode: DetectorMode) {
+        if mode != self.mode {
+            self.mode = mode;
+            self.reset();
+        }
+    }
+
+    /// Sets the sample rate of the detected signal.
+    ///
+    /// This reallocates the history of the true RMS mode, and must not be called from the audio
+    /// thread. The attack and release times need to be set again afterward.
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        let capacity = (MAX_RMS_WINDOW * sample_rate).ceil() as usize;
+        self.history = vec![T::zero(); capacity.max(1)].into_boxed_slice();
+        self.set_window(self.window);
+    }
+
+    /// Sets the length of the RMS averaging window, in seconds, clamped to [`MAX_RMS_WINDOW`].
+    /// This resets the history of the true RMS mode.
+    pub fn set_window(&mut self, window: f64) {
+        self.window = window.clamp(0., MAX_RMS_WINDOW);
+        self.rms_coefficient = T::cast_from(time_to_coefficient(self.sample_rate, self.window));
+        self.history_len =
+            ((self.window * self.sample_rate).round() as usize).clamp(1, self.history.len());
+        self.history.fill(T::zero());
+        self.history_pos = 0;
+        self.history_sum = T::zero();
+    }
+
+    /// Sets the attack and release times of the smoothing applied to the detected level, in seconds.
+    pub fn set_times(&mut self, attack: f64, release: f64) {
+        self.envelope.set_times(self.sample_rate, attack, release);
+    }
+
+    /// Sets whether the detected level is output in decibels instead of as a linear value.
+    pub fn set_log_output(&mut self, log_output: bool) {
+        self.log_output = log_output;
+    }
+
+    /// Resets the detector to silence.
+    pub fn reset(&mut self) {
+        self.mean_square = T::zero();
+        self.history.fill(T::zero());
+        self.history_pos = 0;
+        self.history_sum = T::zero();
+        self.envelope.reset();
+    }
+
+    /// Returns the current detected level.
+    pub fn value(&self) -> T {
+        self.output(self.envelope.value())
+    }
+
+    /// Advances the detector by one sample, returning the new detected level.
+    #[inline]
+    pub fn process(&mut self, x: T) -> T {
+        let x = x.abs();
+        let level = match self.mode {
+            DetectorMode::Peak => x,
+            DetectorMode::Rms => {
+                let square = x * x;
+                self.mean_square = square + self.rms_coefficient * (self.mean_square - square);
+                self.mean_square.sqrt()
+            }
+            DetectorMode::TrueRms => {
+                let square = x * x;
+                self.history_sum = self.history_sum + square - self.history[self.history_pos];
+                self.history[self.history_pos] = square;
+                self.history_pos = (self.history_pos + 1) % self.history_len;
+                let len = T::cast_from(self.history_len as f64);
+                (self.history_sum.max(T::zero()) / len).sqrt()
+            }
+        };
+        let level = self.envelope.process(level);
+        self.output(level)
+    }
+
+    fn output(&self, level: T) -> T {
+        if self.log_output {
+            linear_to_db(level)
+        } else {
+            level
+        }
+    }
+}
+
+/// Inputs of the [`Compressor`] and [`Gate`] modules.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum CompressorInput {
+    /// Left channel of the compressed signal.
+    Left,
+    /// Right channel of the compressed signal.
+    Right,
+    /// Left channel of the sidechain signal.
+    #[display = "Sidechain left"]
+    SidechainLeft,
+    /// Right channel of the sidechain signal.
+    #[display = "Sidechain right"]
+    SidechainRight,
+}
+
+/// Detects the peak level of the loudest channel at index `i`, from either the main or the
+/// sidechain inputs (laid out as [`CompressorInput`]).
+#[inline]
+fn detect_peak<T: Float>(inputs: &[&[T]], sidechain: bool, i: usize) -> T {
+    let (left, right) = if sidechain {
+        (
+            CompressorInput::SidechainLeft,
+            CompressorInput::SidechainRight,
+        )
+    } else {
+        (CompressorInput::Left, CompressorInput::Right)
+    };
+    inputs[left.cast()][i]
+        .abs()
+        .max(inputs[right.cast()][i].abs())
+}
+
+/// Creates the level detector of the [`Compressor`] and [`Gate`] modules, which outputs the level
+/// in decibels without smoothing, as the ballistics are applied to the gain instead.
+fn gain_detector<T: Float + CastFrom<f64>>(sample_rate: f64) -> LevelDetector<T> {
+    let mut detector = LevelDetector::new(sample_rate, DetectorMode::Peak);
+    detector.set_log_output(true);
+    detector
+}
+
+/// Parameters of the [`Compressor`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum CompressorParams {
+    /// Level above which the signal is compressed, in dB.
+    Threshold,
+    /// Compression ratio, 1 or above.
+    Ratio,
+    /// Attack time, in seconds.
+    Attack,
+    /// Release time, in seconds.
+    Release,
+    /// Width of the soft knee around the threshold, in dB.
+    Knee,
+    /// Gain applied after compression, in dB.
+    Makeup,
+    /// Whether the level is detected from the sidechain inputs (0 or 1).
+    Sidechain,
+    /// Level detection mode, as the index of a [`DetectorMode`].
+    Detector,
+}
+
+/// A stereo-linked feed-forward compressor.
+///
+/// The level of the signal is detected from the loudest of both channels, either from the main
+/// inputs or from the sidechain inputs, and the same gain reduction is applied to both channels.
+/// Attack and release are applied to the gain reduction, in decibels.
+#[derive(Debug, Clone)]
+pub struct Compressor<T> {
+    sample_rate: f64,
+    threshold: f32,
+    ratio: f32,
+    attack: f32,
+    release: f32,
+    knee: f32,
+    makeup: f32,
+    sidechain: bool,
+    detector: LevelDetector<T>,
+    envelope: EnvelopeFollower<T>,
+}
+
+impl<T: Float + CastFrom<f64>> Compressor<T> {
+    /// Creates a new compressor, with a threshold of -18 dB, a ratio of 4:1, a 10 ms attack, a 100
+    /// ms release and a 6 dB knee.
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            threshold: -18.,
+            ratio: 4.,
+            attack: 0.01,
+            release: 0.1,
+            knee: 6.,
+            makeup: 0.,
+            sidechain: false,
+            detector: gain_detector(sample_rate),
+            envelope: EnvelopeFollower::new(sample_rate, 0.01, 0.1),
+        }
+    }
+
+    /// Sets the level above which the signal is compressed, in dB.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the compression ratio, clamped to 1 or above.
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.max(1.);
+    }
+
+    /// Sets the attack time, in seconds.
+    pub fn set_attack(&mut self, attack: f32) {
+        self.attack = attack.max(0.);
+        self.update_envelope();
+    }
+
+    /// Sets the release time, in seconds.
+    pub fn set_release(&mut self, release: f32) {
+        self.release = release.max(0.);
+        self.update_envelope();
+    }
+
+    /// Sets the width of the soft knee, in dB. A width of 0 gives a hard knee.
+    pub fn set_knee(&mut self, knee: f32) {
+        self.knee = knee.max(0.);
+    }
+
+    /// Sets the gain applied after compression, in dB.
+    pub fn set_makeup(&mut self, makeup: f32) {
+        self.makeup = makeup;
+    }
+
+    /// Sets whether the level is detected from the sidechain inputs instead of the main inputs.
+    pub fn set_sidechain(&mut self, sidechain: bool) {
+        self.sidechain = sidechain;
+    }
+
+    /// Sets the mode used to detect the level of the signal.
+    pub fn set_detector_mode(&mut self, mode: DetectorMode) {
+        self.detector.set_mode(mode);
+    }
+
+    /// Returns the current gain reduction, in dB (as a positive value).
+    pub fn gain_reduction(&self) -> T {
+        self.envelope.value()
+    }
+
+    /// Computes the static output level of the compressor for a given input level, in dB.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_effects::dynamics::Compressor;
+    /// let mut compressor = Compressor::<f32>::new(44100.0);
+    /// compressor.set_threshold(-20.0);
+    /// compressor.set_ratio(4.0);
+    /// compressor.set_knee(0.0);
+    /// assert_eq!(-30.0, compressor.gain_computer(-30.0));
+    /// assert_eq!(-15.0, compressor.gain_computer(0.0));
+    /// ```
+    #[replace_float_literals(T::cast_from(literal))]
+    pub fn gain_computer(&self, level: T) -> T {
+        let threshold = T::cast_from(self.threshold as f64);
+        let knee = T::cast_from(self.knee as f64);
+        let slope = T::cast_from(self.ratio as f64).recip() - 1.0;
+        let overshoot = level - threshold;
+        if 2.0 * overshoot < -knee {
+            level
+        } else if knee > 0.0 && 2.0 * overshoot.abs() <= knee {
+            let x = overshoot + knee / 2.0;
+            level + slope * x * x / (2.0 * knee)
+        } else {
+            level + slope * overshoot
+        }
+    }
+
+    fn update_envelope(&mut self) {
+        self.envelope
+            .set_times(self.sample_rate, self.attack as _, self.release as _);
+    }
+}
+
+impl<T: 'static + Send + Float + CastFrom<f64>> Module for Compressor<T> {
+    type Sample = T;
+    type Inputs = CompressorInput;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.sample_rate = stream_data.sample_rate;
+        self.detector.set_sample_rate(stream_data.sample_rate);
+        self.update_envelope();
+    }
+
+    fn reset(&mut self) {
+        self.detector.reset();
+        self.envelope.reset();
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|channel| match channel {
+            Stereo::Left => input_latencies[CompressorInput::Left],
+            Stereo::Right => input_latencies[CompressorInput::Right],
+        })
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let makeup = T::cast_from(self.makeup as f64);
+        let block_size = inputs[0].len();
+
+        for i in 0..block_size {
+            let level = self
+                .detector
+                .process(detect_peak(inputs, self.sidechain, i));
+            let reduction = if level.is_finite() {
+                level - self.gain_computer(level)
+            } else {
+                T::zero()
+            };
+            let reduction = self.envelope.process(reduction);
+            let gain = db_to_linear(makeup - reduction);
+            for channel in enum_iter::<Stereo>() {
+                outputs[channel.cast()][i] = gain * inputs[channel.cast()][i];
+            }
+        }
+        ProcessStatus::Running
+    }
+}
+
+impl<T> GetParameter for Compressor<T> {
+    type Param = CompressorParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            CompressorParams::Threshold => Value::Float(self.threshold),
+            CompressorParams::Ratio => Value::Float(self.ratio),
+            CompressorParams::Attack => Value::Float(self.attack),
+            CompressorParams::Release => Value::Float(self.release),
+            CompressorParams::Knee => Value::Float(self.knee),
+            CompressorParams::Makeup => Value::Float(self.makeup),
+            CompressorParams::Sidechain => Value::Int(self.sidechain as i64),
+            CompressorParams::Detector => Value::Int(self.detector.mode().cast() as i64),
+        }
+    }
+}
+
+impl<T: Float + CastFrom<f64>> SetParameter for Compressor<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        match param {
+            CompressorParams::Threshold => {
+                if let Ok(threshold) = f32::try_from(value) {
+                    self.set_threshold(threshold);
+                }
+            }
+            CompressorParams::Ratio => {
+                if let Ok(ratio) = f32::try_from(value) {
+                    self.set_ratio(ratio);
+                }
+            }
+            CompressorParams::Attack => {
+                if let Ok(attack) = f32::try_from(value) {
+                    self.set_attack(attack);
+                }
+            }
+            CompressorParams::Release => {
+                if let Ok(release) = f32::try_from(value) {
+                    self.set_release(release);
+                }
+            }
+            CompressorParams::Knee => {
+                if let Ok(knee) = f32::try_from(value) {
+                    self.set_knee(knee);
+                }
+            }
+            CompressorParams::Makeup => {
+                if let Ok(makeup) = f32::try_from(value) {
+                    self.set_makeup(makeup);
+                }
+            }
+            CompressorParams::Sidechain => {
+                if let Ok(sidechain) = i64::try_from(value) {
+                    self.set_sidechain(sidechain != 0);
+                }
+            }
+            CompressorParams::Detector => {
+                if let Ok(mode) = i64::try_from(value) {
+                    self.set_detector_mode(from_index_clamped(mode));
+                }
+            }
+        }
+    }
+}
+
+/// Parameters of the [`Gate`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum GateParams {
+    /// Level above which the gate opens, in dB.
+    Threshold,
+    /// Distance below the threshold at which the gate closes again, in dB.
+    Hysteresis,
+    /// Time the gate stays open after the level falls below the closing level, in seconds.
+    Hold,
+    /// Attack time, in seconds.
+    Attack,
+    /// Release time, in seconds.
+    Release,
+    /// Gain applied while the gate is closed, in dB.
+    Range,
+    /// Whether the level is detected from the sidechain inputs (0 or 1).
+    Sidechain,
+    /// Level detection mode, as the index of a [`DetectorMode`].
+    Detector,
+}
+
+/// A stereo-linked noise gate, with hysteresis, hold and an optional sidechain input.
+///
+/// The gate opens when the detected level rises above the threshold, and closes once it has
+/// stayed below the threshold minus the hysteresis for the hold time. The opening and closing
+/// of the gate are smoothed by the attack and release times.
+///
+/// While closed, the gate attenuates the signal by its range, so that a small range makes it act
+/// as a downward expander instead of muting the signal.
+#[derive(Debug, Clone)]
+pub struct Gate<T> {
+    sample_rate: f64,
+    threshold: f32,
+    hysteresis: f32,
+    hold: f32,
+    attack: f32,
+    release: f32,
+    range: f32,
+    sidechain: bool,
+    open: bool,
+    hold_remaining: usize,
+    detector: LevelDetector<T>,
+    envelope: EnvelopeFollower<T>,
+}
+
+impl<T: Float + CastFrom<f64>> Gate<T> {
+    /// Creates a new gate, with a threshold of -40 dB, a hysteresis of 6 dB, a 50 ms hold, a 1 ms
+    /// attack, a 100 ms release and a range of -80 dB.
+    pub fn new(sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            threshold: -40.,
+            hysteresis: 6.,
+            hold: 0.05,
+            attack: 0.001,
+            release: 0.1,
+            range: -80.,
+            sidechain: false,
+            open: false,
+            hold_remaining: 0,
+            detector: gain_detector(sample_rate),
+            envelope: EnvelopeFollower::new(sample_rate, 0.001, 0.1),
+        }
+    }
+
+    /// Sets the level above which the gate opens, in dB.
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the distance below the threshold at which the gate closes again, in dB.
+    pub fn set_hysteresis(&mut self, hysteresis: f32) {
+        self.hysteresis = hysteresis.max(0.);
+    }
+
+    /// Sets the time the gate stays open after the level falls below the closing level, in
+    /// seconds.
+    pub fn set_hold(&mut self, hold: f32) {
+        self.hold = hold.max(0.);
+    }
+
+    /// Sets the attack time, in seconds.
+    pub fn set_attack(&mut self, attack: f32) {
+        self.attack = attack.max(0.);
+        self.update_envelope();
+    }
+
+    /// Sets the release time, in seconds.
+    pub fn set_release(&mut self, release: f32) {
+        self.release = release.max(0.);
+        self.update_envelope();
+    }
+
+    /// Sets the gain applied while the gate is closed, in dB, clamped to 0 dB or below.
+    pub fn set_range(&mut self, range: f32) {
+        self.range = range.min(0.);
+    }
+
+    /// Sets whether the level is detected from the sidechain inputs instead of the main inputs.
+    pub fn set_sidechain(&mut self, sidechain: bool) {
+        self.sidechain = sidechain;
+    }
+
+    /// Sets the mode used to detect the level of the signal.
+    pub fn set_detector_mode(&mut self, mode: DetectorMode) {
+        self.detector.set_mode(mode);
+    }
+
+    /// Returns whether the gate is currently open (including while holding).
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Returns the gain currently applied by the gate, as a linear gain.
+    pub fn gain(&self) -> T {
+        self.envelope.value()
+    }
+
+    fn hold_samples(&self) -> usize {
+        (self.hold as f64 * self.sample_rate).round() as usize
+    }
+
+    fn update_envelope(&mut self) {
+        self.envelope
+            .set_times(self.sample_rate, self.attack as _, self.release as _);
+    }
+}
+
+impl<T: 'static + Send + Float + CastFrom<f64>> Module for Gate<T> {
+    type Sample = T;
+    type Inputs = CompressorInput;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.sample_rate = stream_data.sample_rate;
+        self.detector.set_sample_rate(stream_data.sample_rate);
+        self.update_envelope();
+    }
+
+    fn reset(&mut self) {
+        self.open = false;
+        self.hold_remaining = 0;
+        self.detector.reset();
+        self.envelope.reset();
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|channel| match channel {
+            Stereo::Left => input_latencies[CompressorInput::Left],
+            Stereo::Right => input_latencies[CompressorInput::Right],
+        })
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let open_level = T::cast_from(self.threshold as f64);
+        let close_level = T::cast_from((self.threshold - self.hysteresis) as f64);
+        let closed_gain = db_to_linear(T::cast_from(self.range as f64));
+        let hold = self.hold_samples();
+        let block_size = inputs[0].len();
+
+        for i in 0..block_size {
+            let level = self
+                .detector
+                .process(detect_peak(inputs, self.sidechain, i));
+            if level > open_level || (self.open && level > close_level) {
+                self.open = true;
+                self.hold_remaining = hold;
+            } else if self.hold_remaining > 0 {
+                self.hold_remaining -= 1;
+            } else {
+                self.open = false;
+            }
+
+            let target = if self.open { T::one() } else { closed_gain };
+            let gain = self.envelope.process(target);
+            for channel in enum_iter::<Stereo>() {
+                outputs[channel.cast()][i] = gain * inputs[channel.cast()][i];
+            }
+        }
+        ProcessStatus::Running
+    }
+}
+
+impl<T> GetParameter for Gate<T> {
+    type Param = GateParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            GateParams::Threshold => Value::Float(self.threshold),
+            GateParams::Hysteresis => Value::Float(self.hysteresis),
+            GateParams::Hold => Value::Float(self.hold),
+            GateParams::Attack => Value::Float(self.attack),
+            GateParams::Release => Value::Float(self.release),
+            GateParams::Range => Value::Float(self.range),
+            GateParams::Sidechain => Value::Int(self.sidechain as i64),
+            GateParams::Detector => Value::Int(self.detector.mode().cast() as i64),
+        }
+    }
+}
+
+impl<T: Float + CastFrom<f64>> SetParameter for Gate<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        match param {
+            GateParams::Threshold => {
+                if let Ok(threshold) = f32::try_from(value) {
+                    self.set_threshold(threshold);
+                }
+            }
+            GateParams::Hysteresis => {
+                if let Ok(hysteresis) = f32::try_from(value) {
+                    self.set_hysteresis(hysteresis);
+                }
+            }
+            GateParams::Hold => {
+                if let Ok(hold) = f32::try_from(value) {
+                    self.set_hold(hold);
+                }
+            }
+            GateParams::Attack => {
+                if let Ok(attack) = f32::try_from(value) {
+                    self.set_attack(attack);
+                }
+            }
+            GateParams::Release => {
+                if let Ok(release) = f32::try_from(value) {
+                    self.set_release(release);
+                }
+            }
+            GateParams::Range => {
+                if let Ok(range) = f32::try_from(value) {
+                    self.set_range(range);
+                }
+            }
+            GateParams::Sidechain => {
+                if let Ok(sidechain) = i64::try_from(value) {
+                    self.set_sidechain(sidechain != 0);
+                }
+            }
+            GateParams::Detector => {
+                if let Ok(mode) = i64::try_from(value) {
+                    self.set_detector_mode(from_index_clamped(mode));
+                }
+            }
+        }
+    }
+}
+
+/// Parameters of the [`Limiter`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum LimiterParams {
+    /// Maximum output level, in dB.
+    Ceiling,
+    /// Lookahead time, in seconds.
+    Lookahead,
+    /// Release time, in seconds.
+    Release,
+    /// Whether inter-sample peaks are estimated when detecting the level (0 or 1).
+    #[display = "True peak"]
+    TruePeak,
+}
+
+/// A stereo-linked brickwall limiter with lookahead.
+///
+/// The audio is delayed by the lookahead time, which lets the gain reduction ramp down smoothly
+/// before a peak reaches the output, instead of clipping it. The gain then recovers exponentially
+/// with the release time. The lookahead is reported as the latency of the module.
+///
+/// When true peak detection is enabled, the level between samples is estimated by cubic
+/// interpolation, which catches peaks that would only appear after conversion to analog (or
+/// resampling). This adds 2 samples of latency.
+#[derive(Debug, Clone)]
+pub struct Limiter<T> {
+    sample_rate: f64,
+    ceiling: f32,
+    lookahead: f32,
+    release: f32,
+    true_peak: bool,
+    audio: [DelayBuffer<T>; 2],
+    history: [[T; 4]; 2],
+    required: DelayBuffer<T>,
+    held: T,
+    hold_remaining: usize,
+    release_state: T,
+    release_coefficient: T,
+    smoothing: DelayBuffer<T>,
+    smoothing_sum: T,
+}
+
+impl<T: Float + CastFrom<f64> + Cast<usize>> Limiter<T> {
+    /// Maximum lookahead time, in seconds.
+    pub const MAX_LOOKAHEAD: f32 = 0.02;
+
+    /// Creates a new limiter, with a ceiling of -0.1 dB, a 5 ms lookahead and a 50 ms release.
+    pub fn new(sample_rate: f64) -> Self {
+        let capacity = (Self::MAX_LOOKAHEAD as f64 * sample_rate).ceil() as usize + 3;
+        let mut this = Self {
+            sample_rate,
+            ceiling: -0.1,
+            lookahead: 0.005,
+            release: 0.05,
+            true_peak: false,
+            audio: [(); 2].map(|_| DelayBuffer::new(capacity)),
+            history: [[T::zero(); 4]; 2],
+            required: DelayBuffer::new(capacity),
+            held: T::zero(),
+            hold_remaining: 0,
+            release_state: T::zero(),
+            release_coefficient: T::zero(),
+            smoothing: DelayBuffer::new(capacity),
+            smoothing_sum: T::zero(),
+        };
+        this.update_release();
+        this
+    }
+
+    /// Sets the maximum output level, in dB.
+    pub fn set_ceiling(&mut self, ceiling: f32) {
+        self.ceiling = ceiling;
+    }
+
+    /// Sets the lookahead time, in seconds, clamped to [`Self::MAX_LOOKAHEAD`].
+    ///
+    /// Changing the lookahead changes the latency of the module, and resets its gain reduction.
+    pub fn set_lookahead(&mut self, lookahead: f32) {
+        self.lookahead = lookahead.clamp(0., Self::MAX_LOOKAHEAD);
+        self.reset_gain();
+    }
+
+    /// Sets the release time, in seconds.
+    pub fn set_release(&mut self, release: f32) {
+        self.release = release.max(0.);
+        self.update_release();
+    }
+
+    /// Sets whether inter-sample peaks are estimated when detecting the level.
+    ///
+    /// Changing this changes the latency of the module.
+    pub fn set_true_peak(&mut self, true_peak: bool) {
+        self.true_peak = true_peak;
+    }
+
+    /// Returns the lookahead time, in samples.
+    pub fn lookahead_samples(&self) -> usize {
+        (self.lookahead as f64 * self.sample_rate).round() as usize
+    }
+
+    /// Returns the latency introduced by the limiter, in samples.
+    pub fn latency_samples(&self) -> usize {
+        self.lookahead_samples() + if self.true_peak { 2 } else { 0 }
+    }
+
+    /// Returns the current gain reduction, as a linear gain.
+    pub fn gain(&self) -> T {
+        T::one() - self.smoothing_sum / T::cast_from((self.lookahead_samples() + 1) as f64)
+    }
+
+    fn update_release(&mut self) {
+        self.release_coefficient =
+            T::cast_from(time_to_coefficient(self.sample_rate, self.release as _));
+    }
+
+    fn reset_gain(&mut self) {
+        self.required.clear();
+        self.smoothing.clear();
+        self.held = T::zero();
+        self.hold_remaining = 0;
+        self.release_state = T::zero();
+        self.smoothing_sum = T::zero();
+    }
+
+    /// Pushes a new input sample into the history of a channel, and returns its detected peak.
+    #[replace_float_literals(T::cast_from(literal))]
+    fn detect(&mut self, channel: usize, x: T) -> T {
+        let history = &mut self.history[channel];
+        history.rotate_left(1);
+        history[3] = x;
+        if !self.true_peak {
+            return x.abs();
+        }
+        // Detect the peak over the segment between the second and third samples, which are 2 and
+        // 1 samples old, respectively
+        [1.25, 1.5, 1.75]
+            .into_iter()
+            .map(|index| Cubic.interpolate(&&history[..], index).abs())
+            .fold(history[1].abs(), T::max)
+    }
+}
+
+impl<T: 'static + Send + Float + CastFrom<f64> + Cast<usize>> Module for Limiter<T> {
+    type Sample = T;
+    type Inputs = Stereo;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        if stream_data.sample_rate != self.sample_rate {
+            let Self {
+                ceiling,
+                lookahead,
+                release,
+                true_peak,
+                ..
+            } = *self;
+            *self = Self {
+                ceiling,
+                lookahead,
+                release,
+                true_peak,
+                ..Self::new(stream_data.sample_rate)
+            };
+            self.update_release();
+        }
+    }
+
+    fn reset(&mut self) {
+        for buffer in &mut self.audio {
+            buffer.clear();
+        }
+        self.history = [[T::zero(); 4]; 2];
+        self.reset_gain();
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let latency = self.latency_samples() as f64;
+        EnumMapArray::new(|channel| input_latencies[channel] + latency)
+    }
+
+    #[profiling::function]
+    #[replace_float_literals(T::cast_from(literal))]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let ceiling = db_to_linear(T::cast_from(self.ceiling as f64));
+        let window = self.lookahead_samples() + 1;
+        let window_len = T::cast_from(window as f64);
+        let delay = self.latency_samples();
+        let block_size = inputs[0].len();
+
+        for i in 0..block_size {
+            let mut peak = T::zero();
+            for channel in enum_iter::<Stereo>() {
+                let x = inputs[channel.cast()][i];
+                self.audio[channel.cast()].push(x);
+                peak = peak.max(self.detect(channel.cast(), x));
+            }
+
+            // Gains are handled as reductions (1 - gain), so that silent buffers mean no reduction
+            let required = if peak > ceiling {
+                1.0 - ceiling / peak
+            } else {
+                0.0
+            };
+            self.required.push(required);
+
+            // Hold the maximum reduction over the lookahead window
+            if required >= self.held {
+                self.held = required;
+                self.hold_remaining = window;
+            } else {
+                self.hold_remaining = self.hold_remaining.saturating_sub(1);
+                if self.hold_remaining == 0 {
+                    let (age, held) = (0..window)
+                        .map(|age| (age, self.required.get(age)))
+                        .fold((0, T::zero()), |acc, x| if x.1 > acc.1 { x } else { acc });
+                    self.held = held;
+                    self.hold_remaining = window - age;
+                }
+            }
+
+            // Instant attack, exponential release
+            self.release_state = if self.held > self.release_state {
+                self.held
+            } else {
+                self.held + self.release_coefficient * (self.release_state - self.held)
+            };
+
+            // Moving average over the window, such that the reduction is fully applied by the time
+            // the peak reaches the output
+            let oldest = self.smoothing.get(window - 1);
+            self.smoothing.push(self.release_state);
+            self.smoothing_sum = (self.smoothing_sum + self.release_state - oldest).max(0.0);
+            let gain = 1.0 - self.smoothing_sum / window_len;
+
+            for channel in enum_iter::<Stereo>() {
+                let delayed = self.audio[channel.cast()].get(delay);
+                outputs[channel.cast()][i] = gain * delayed;
+            }
+        }
+        ProcessStatus::Tail(delay as u64)
+    }
+}
+
+impl<T> GetParameter for Limiter<T> {
+    type Param = LimiterParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            LimiterParams::Ceiling => Value::Float(self.ceiling),
+            LimiterParams::Lookahead => Value::Float(self.lookahead),
+            LimiterParams::Release => Value::Float(self.release),
+            LimiterParams::TruePeak => Value::Int(self.true_peak as i64),
+        }
+    }
+}
+
+impl<T: Float + CastFrom<f64> + Cast<usize>> SetParameter for Limiter<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        match param {
+            LimiterParams::Ceiling => {
+                if let Ok(ceiling) = f32::try_from(value) {
+                    self.set_ceiling(ceiling);
+                }
+            }
+            LimiterParams::Lookahead => {
+                if let Ok(lookahead) = f32::try_from(value) {
+                    self.set_lookahead(lookahead);
+                }
+            }
+            LimiterParams::Release => {
+                if let Ok(release) = f32::try_from(value) {
+                    self.set_release(release);
+                }
+            }
+            LimiterParams::TruePeak => {
+                if let Ok(true_peak) = i64::try_from(value) {
+                    self.set_true_peak(true_peak != 0);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    const STREAM_DATA: StreamData = StreamData::new(1000., 120., 1000);
+
+    fn process(compressor: &mut Compressor<f64>, main: f64, sidechain: f64) -> [Vec<f64>; 2] {
+        let main = vec![main; STREAM_DATA.block_size];
+        let sidechain = vec![sidechain; STREAM_DATA.block_size];
+        let mut outputs = [vec![0.; main.len()], vec![0.; main.len()]];
+        let [left, right] = &mut outputs;
+        compressor.process(
+            &STREAM_DATA,
+            &[&main, &main, &sidechain, &sidechain],
+            &mut [left, right],
+        );
+        outputs
+    }
+
+    #[rstest]
+    #[case(-40., -40.)]
+    #[case(-23., -23.)]
+    #[case(-20., -20.5625)]
+    #[case(-17., -19.25)]
+    #[case(0., -15.)]
+    fn test_soft_knee_gain_computer(#[case] level: f64, #[case] expected: f64) {
+        let mut compressor = Compressor::new(STREAM_DATA.sample_rate);
+        compressor.set_threshold(-20.);
+        compressor.set_ratio(4.);
+        compressor.set_knee(6.);
+        assert_relative_eq!(expected, compressor.gain_computer(level));
+    }
+
+    #[rstest]
+    fn test_steady_state_reduction() {
+        let mut compressor = Compressor::new(STREAM_DATA.sample_rate);
+        compressor.set_threshold(-20.);
+        compressor.set_ratio(2.);
+        compressor.set_knee(0.);
+        compressor.set_makeup(3.);
+        let [left, right] = process(&mut compressor, 1., 0.);
+        assert_relative_eq!(10., compressor.gain_reduction(), epsilon = 1e-3);
+        assert_relative_eq!(db_to_linear(-7.), left[999], epsilon = 1e-3);
+        assert_eq!(left, right);
+    }
+
+    #[rstest]
+    fn test_below_threshold_is_transparent() {
+        let mut compressor = Compressor::new(STREAM_DATA.sample_rate);
+        let [left, _] = process(&mut compressor, 0.01, 1.);
+        assert!(left.iter().all(|x| *x == 0.01));
+    }
+
+    #[rstest]
+    fn test_sidechain_drives_reduction() {
+        let mut compressor = Compressor::new(STREAM_DATA.sample_rate);
+        compressor.set_param(CompressorParams::Sidechain, 1);
+        let [left, _] = process(&mut compressor, 0.01, 1.);
+        assert!(compressor.gain_reduction() > 10.);
+        assert!(left[999] < 0.01 * db_to_linear(-10.));
+    }
+
+    #[rstest]
+    #[case(DetectorMode::Peak, 1., 0.02)]
+    #[case(DetectorMode::Rms, std::f64::consts::FRAC_1_SQRT_2, 0.05)]
+    #[case(DetectorMode::TrueRms, std::f64::consts::FRAC_1_SQRT_2, 1e-9)]
+    fn test_detector_modes(
+        #[case] mode: DetectorMode,
+        #[case] expected: f64,
+        #[case] epsilon: f64,
+    ) {
+        let mut detector = LevelDetector::<f64>::new(1000., mode);
+        detector.set_window(0.02);
+        detector.set_times(0., 1.);
+        // Sine at 50 Hz, with a whole number of periods in the RMS window
+        for i in 0..1000 {
+            detector.process((std::f64::consts::TAU * 50. * i as f64 / 1000.).sin());
+        }
+        assert_relative_eq!(expected, detector.value(), epsilon = epsilon);
+    }
+
+    #[rstest]
+    fn test_detector_log_output() {
+        let mut detector = LevelDetector::<f64>::new(1000., DetectorMode::Peak);
+        detector.set_log_output(true);
+        assert_relative_eq!(-6.0206, detector.process(-0.5), epsilon = 1e-4);
+        assert_eq!(f64::NEG_INFINITY, detector.process(0.));
+    }
+
+    #[rstest]
+    fn test_compressor_rms_detection() {
+        let mut compressor = Compressor::new(STREAM_DATA.sample_rate);
+        compressor.set_threshold(-20.);
+        compressor.set_knee(0.);
+        compressor.set_param(
+            CompressorParams::Detector,
+            DetectorMode::TrueRms.cast() as i64,
+        );
+        assert_eq!(
+            Value::Int(2),
+            compressor.get_param_raw(CompressorParams::Detector)
+        );
+        // Square wave, whose RMS level equals its peak level
+        let main = Vec::from_iter((0..1000).map(|i| if i % 2 == 0 { 1. } else { -1. }));
+        let (mut left, mut right) = (vec![0.; 1000], vec![0.; 1000]);
+        compressor.process(
+            &STREAM_DATA,
+            &[&main, &main, &main, &main],
+            &mut [&mut left, &mut right],
+        );
+        assert_relative_eq!(15., compressor.gain_reduction(), epsilon = 1e-3);
+    }
+
+    #[rstest]
+    fn test_envelope_follower_ballistics() {
+        let mut follower = EnvelopeFollower::<f64>::new(1000., 0.01, 0.1);
+        for _ in 0..10 {
+            follower.process(1.);
+        }
+        assert_relative_eq!(1. - (-1f64).exp(), follower.value(), epsilon = 1e-9);
+        for _ in 0..100 {
+            follower.process(0.);
+        }
+        assert_relative_eq!(
+            follower.value(),
+            (1. - (-1f64).exp()) * (-1f64).exp(),
+            epsilon = 1e-9
+        );
+    }
+
+    fn process_gate(gate: &mut Gate<f64>, main: &[f64], sidechain: f64) -> Vec<f64> {
+        let sidechain = vec![sidechain; main.len()];
+        let mut output = vec![0.; main.len()];
+        let mut right = vec![0.; main.len()];
+        gate.process(
+            &STREAM_DATA,
+            &[main, main, &sidechain, &sidechain],
+            &mut [&mut output, &mut right],
+        );
+        assert_eq!(output, right);
+        output
+    }
+
+    fn instant_gate() -> Gate<f64> {
+        let mut gate = Gate::new(STREAM_DATA.sample_rate);
+        gate.set_attack(0.);
+        gate.set_release(0.);
+        gate
+    }
+
+    #[rstest]
+    fn test_gate_hold() {
+        let mut gate = instant_gate();
+        let mut input = vec![1.; 100];
+        input.resize(200, 0.001);
+        let output = process_gate(&mut gate, &input, 0.);
+        assert_eq!(&input[..150], &output[..150]);
+        assert_relative_eq!(0.001 * db_to_linear(-80.), output[150], epsilon = 1e-12);
+        assert!(!gate.is_open());
+    }
+
+    #[rstest]
+    #[case(db_to_linear(-10.), true)]
+    #[case(db_to_linear(-30.), false)]
+    fn test_gate_hysteresis(#[case] first: f64, #[case] expected_open: bool) {
+        let mut gate = instant_gate();
+        gate.set_threshold(-20.);
+        gate.set_hysteresis(6.);
+        gate.set_hold(0.);
+        let mut input = vec![first; 10];
+        input.resize(100, db_to_linear(-23.));
+        process_gate(&mut gate, &input, 0.);
+        assert_eq!(expected_open, gate.is_open());
+    }
+
+    #[rstest]
+    fn test_gate_sidechain_opens() {
+        let mut gate = instant_gate();
+        gate.set_param(GateParams::Sidechain, 1);
+        let output = process_gate(&mut gate, &[0.001; 10], 1.);
+        assert!(gate.is_open());
+        assert_eq!(vec![0.001; 10], output);
+    }
+
+    fn limit(limiter: &mut Limiter<f64>, input: &[f64]) -> Vec<f64> {
+        let mut output = vec![0.; input.len()];
+        let mut right = vec![0.; input.len()];
+        limiter.process(
+            &STREAM_DATA,
+            &[input, input],
+            &mut [&mut output, &mut right],
+        );
+        assert_eq!(output, right);
+        output
+    }
+
+    #[rstest]
+    #[case(false, 5)]
+    #[case(true, 7)]
+    fn test_limiter_latency(#[case] true_peak: bool, #[case] expected: usize) {
+        let mut limiter = Limiter::<f64>::new(STREAM_DATA.sample_rate);
+        limiter.set_param(LimiterParams::TruePeak, true_peak as i64);
+        assert_eq!(expected, limiter.latency_samples());
+        let latency = limiter.latency(EnumMapArray::new(|_| 1.));
+        assert_eq!([expected as f64 + 1.; 2], latency.into_inner().into_array());
+    }
+
+    #[rstest]
+    fn test_limiter_quiet_signal_is_delayed() {
+        let mut limiter = Limiter::new(STREAM_DATA.sample_rate);
+        let input = Vec::from_iter((0..100).map(|i| 0.5 * (i as f64 * 0.1).sin()));
+        let output = limit(&mut limiter, &input);
+        assert_eq!(&input[..95], &output[5..]);
+    }
+
+    #[rstest]
+    #[case(false)]
+    #[case(true)]
+    fn test_limiter_never_exceeds_ceiling(#[case] true_peak: bool) {
+        let mut limiter = Limiter::new(STREAM_DATA.sample_rate);
+        limiter.set_ceiling(-6.);
+        limiter.set_release(0.);
+        limiter.set_true_peak(true_peak);
+        // Isolated peaks of varying heights exercise the hold, on top of a loud sine
+        let input = Vec::from_iter((0..1000).map(|i| match i % 7 {
+            0 => 1. + (i % 11) as f64 * 0.3,
+            _ => 4. * (i as f64 * 0.37).sin() * (i as f64 * 0.01).sin(),
+        }));
+        let output = limit(&mut limiter, &input);
+        // The output is not clipped, so this checks the lookahead and hold of the gain reduction
+        let ceiling = db_to_linear(-6.) * (1. + 1e-9);
+        assert!(output.iter().all(|x| x.abs() <= ceiling));
+    }
+
+    #[rstest]
+    fn test_limiter_ramps_before_peak() {
+        let mut limiter = Limiter::new(STREAM_DATA.sample_rate);
+        limiter.set_ceiling(0.);
+        let mut input = vec![0.5; 100];
+        input[50] = 2.;
+        let output = limit(&mut limiter, &input);
+        // The peak is reached exactly at the ceiling, and the reduction starts ahead of it
+        assert_relative_eq!(1., output[55], epsilon = 1e-9);
+        assert_eq!(0.5, output[49]);
+        assert!(output[50] < 0.5);
+        assert!(output[54] < output[53]);
+        assert!(output[56] < 0.5);
+    }
+}
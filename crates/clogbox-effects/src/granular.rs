@@ -0,0 +1,505 @@
+//! Granular synthesis.
+//!
+//! This module provides [`Granular`], a module which continuously spawns short, windowed grains
+//! read from a [`SampleBuffer`] (shared through a [`BufferSlot`]), and spreads them across the
+//! stereo field.
+//!
+//! Grains are taken from a fixed pool of [`MAX_GRAINS`] grains, so that processing never
+//! allocates; new grains are dropped while the pool is full.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_effects::granular::Granular;
+//! use clogbox_effects::sampler::{BufferSlot, SampleBuffer};
+//!
+//! let stream_data = StreamData::new(44100.0, 120.0, 64);
+//! let slot = BufferSlot::new();
+//! slot.store(SampleBuffer::from_channels(44100.0, vec![vec![0.5; 44100]]).unwrap());
+//!
+//! let mut granular = Granular::<f32>::new(slot.clone(), 0);
+//! granular.set_density(50.0);
+//! granular.set_size(0.05);
+//! let (mut left, mut right) = ([0.0; 64], [0.0; 64]);
+//! granular.process(&stream_data, &[], &mut [&mut left, &mut right]);
+//! ```
+use crate::sampler::{BufferSlot, SampleBuffer};
+use az::{Cast, CastFrom};
+use clogbox_core::module::stereo::Stereo;
+use clogbox_core::module::utilitarian::Pan;
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::param::value::Value;
+use clogbox_core::param::{GetParameter, SetParameter};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::{from_index_clamped, Empty};
+use clogbox_derive::Enum;
+use num_traits::Float;
+use std::sync::Arc;
+
+/// Maximum number of grains playing at the same time.
+pub const MAX_GRAINS: usize = 64;
+
+/// Window applied to the amplitude of each grain.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum GrainWindow {
+    /// Raised cosine window.
+    #[default]
+    Hann,
+    /// Triangular window.
+    Triangle,
+    /// Rectangular window, which leaves the grains unchanged.
+    Rectangle,
+}
+
+impl GrainWindow {
+    /// Returns the amplitude of the window at the relative position `x` (in `0..1`) of a grain.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_effects::granular::GrainWindow;
+    /// assert_eq!(1.0, GrainWindow::Hann.value(0.5));
+    /// assert_eq!(0.5, GrainWindow::Triangle.value(0.25));
+    /// ```
+    pub fn value(&self, x: f32) -> f32 {
+        match self {
+            Self::Hann => 0.5 - 0.5 * (std::f32::consts::TAU * x).cos(),
+            Self::Triangle => 1. - (2. * x - 1.).abs(),
+            Self::Rectangle => 1.,
+        }
+    }
+}
+
+/// Parameters of the [`Granular`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum GranularParams {
+    /// Position in the buffer at which grains start, relative to its length (0..1).
+    Position,
+    /// Length of each grain (s).
+    Size,
+    /// Number of grains spawned per second.
+    Density,
+    /// Amount of randomization of the grain positions and onsets (0..1).
+    Jitter,
+    /// Transposition of the grains (semitones, -48..48).
+    Pitch,
+    /// Window applied to the grains, as the index of a [`GrainWindow`].
+    Window,
+    /// Amount of random panning of the grains (0..1).
+    Spread,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Grain {
+    position: f64,
+    step: f64,
+    age: usize,
+    length: usize,
+    gains: (f32, f32),
+    active: bool,
+}
+
+/// Xorshift pseudo-random number generator, used to randomize the grains. Randomness is not
+/// critical here, but the generator needs to be real-time safe and reproducible.
+#[derive(Debug, Copy, Clone)]
+struct Rng(u32);
+
+impl Rng {
+    fn new(seed: u32) -> Self {
+        Self(seed.max(1))
+    }
+
+    /// Returns a random number in `0..1`.
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+
+    /// Returns a random number in `-1..1`.
+    fn next_bipolar(&mut self) -> f32 {
+        2. * self.next_f32() - 1.
+    }
+}
+
+/// Granular engine playing grains of the buffer held by a [`BufferSlot`].
+///
+/// The output is normalized by the square root of the average number of overlapping grains, so
+/// that its loudness stays roughly constant when the density or size change.
+#[derive(Debug)]
+pub struct Granular<T> {
+    slot: BufferSlot<T>,
+    buffer: Option<Arc<SampleBuffer<T>>>,
+    grains: [Grain; MAX_GRAINS],
+    position: f32,
+    size: f32,
+    density: f32,
+    jitter: f32,
+    pitch: f32,
+    window: GrainWindow,
+    spread: f32,
+    phase: f64,
+    rng: Rng,
+    sample_rate: f64,
+}
+
+impl<T> Granular<T> {
+    /// Minimum size of a grain (s).
+    pub const MIN_SIZE: f32 = 0.001;
+    /// Maximum size of a grain (s).
+    pub const MAX_SIZE: f32 = 2.;
+    /// Maximum number of grains spawned per second.
+    pub const MAX_DENSITY: f32 = 1000.;
+    /// Maximum transposition of the grains, up or down (semitones).
+    pub const MAX_PITCH: f32 = 48.;
+
+    /// Creates a new granular engine, playing the buffers stored in the given slot.
+    ///
+    /// The engine defaults to 10 grains per second of 100 ms, taken from the start of the buffer
+    /// with a Hann window, without jitter, transposition or spread.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - Slot holding the buffer to take grains from.
+    /// * `seed` - Seed of the random number generator used to randomize the grains.
+    pub fn new(slot: BufferSlot<T>, seed: u32) -> Self {
+        Self {
+            slot,
+            buffer: None,
+            grains: [Grain::default(); MAX_GRAINS],
+            position: 0.,
+            size: 0.1,
+            density: 10.,
+            jitter: 0.,
+            pitch: 0.,
+            window: GrainWindow::default(),
+            spread: 0.,
+            phase: 1.,
+            rng: Rng::new(seed),
+            sample_rate: 44100.,
+        }
+    }
+
+    /// Sets the position at which grains start, relative to the length of the buffer (0..1).
+    pub fn set_position(&mut self, position: f32) {
+        self.position = position.clamp(0., 1.);
+    }
+
+    /// Sets the length of each grain, in seconds.
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size.clamp(Self::MIN_SIZE, Self::MAX_SIZE);
+    }
+
+    /// Sets the number of grains spawned per second.
+    pub fn set_density(&mut self, density: f32) {
+        self.density = density.clamp(0., Self::MAX_DENSITY);
+    }
+
+    /// Sets the amount of randomization of the grain positions and onsets (0..1).
+    pub fn set_jitter(&mut self, jitter: f32) {
+        self.jitter = jitter.clamp(0., 1.);
+    }
+
+    /// Sets the transposition of the grains, in semitones, clamped to
+    /// -[`Self::MAX_PITCH`]..[`Self::MAX_PITCH`].
+    ///
+    /// Grains last for the same time regardless of their transposition, so that very high pitches
+    /// would only read a few samples of the buffer.
+    pub fn set_pitch(&mut self, pitch: f32) {
+        self.pitch = pitch.clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+    }
+
+    /// Sets the window applied to the grains.
+    pub fn set_window(&mut self, window: GrainWindow) {
+        self.window = window;
+    }
+
+    /// Sets the amount of random panning of the grains (0..1).
+    pub fn set_spread(&mut self, spread: f32) {
+        self.spread = spread.clamp(0., 1.);
+    }
+
+    /// Returns the number of grains currently playing.
+    pub fn active_grains(&self) -> usize {
+        self.grains.iter().filter(|grain| grain.active).count()
+    }
+
+    fn spawn(&mut self, buffer_len: usize, buffer_sample_rate: f64) {
+        let Some(grain) = self.grains.iter_mut().find(|grain| !grain.active) else {
+            return;
+        };
+        let length = (self.size as f64 * self.sample_rate).round().max(1.);
+        let offset = self.jitter * self.rng.next_bipolar() * self.size * buffer_sample_rate as f32;
+        let start = self.position as f64 * buffer_len as f64 + offset as f64;
+        *grain = Grain {
+            position: start.clamp(0., (buffer_len - 1) as f64),
+            step: buffer_sample_rate / self.sample_rate * (self.pitch as f64 / 12.).exp2(),
+            age: 0,
+            length: length as usize,
+            gains: Pan::<f32>::gains(self.spread * self.rng.next_bipolar()),
+            active: true,
+        };
+    }
+}
+
+impl<T: 'static + Send + Sync + Float + CastFrom<f64> + Cast<usize>> Module for Granular<T> {
+    type Sample = T;
+    type Inputs = Empty;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.sample_rate = stream_data.sample_rate;
+    }
+
+    fn reset(&mut self) {
+        self.grains.fill(Grain::default());
+        self.phase = 1.;
+    }
+
+    fn latency(&self, _: EnumMapArray<Self::Inputs, f64>) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|_| 0.)
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        _: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        self.slot.fetch(&mut self.buffer);
+        let (left, right) = outputs.split_at_mut(1);
+        let (left, right) = (&mut *left[0], &mut *right[0]);
+        left.fill(T::zero());
+        right.fill(T::zero());
+        let Some(buffer) = self.buffer.clone().filter(|buffer| !buffer.is_empty()) else {
+            return ProcessStatus::Tail(0);
+        };
+
+        let increment = self.density as f64 / stream_data.sample_rate;
+        let gain = (self.density * self.size).max(1.).sqrt().recip();
+        for i in 0..stream_data.block_size {
+            while self.phase >= 1. {
+                // Onsets are jittered evenly around the period, so that the average density is kept
+                self.phase += 0.5 * self.jitter as f64 * self.rng.next_bipolar() as f64 - 1.;
+                self.spawn(buffer.len(), buffer.sample_rate());
+            }
+            self.phase += increment;
+
+            let (mut sum_left, mut sum_right) = (T::zero(), T::zero());
+            for grain in self.grains.iter_mut().filter(|grain| grain.active) {
+                let window = self.window.value(grain.age as f32 / grain.length as f32);
+                let (frame_left, frame_right) = buffer.frame(grain.position);
+                let (gain_left, gain_right) = grain.gains;
+                sum_left = sum_left + T::cast_from((window * gain * gain_left) as f64) * frame_left;
+                sum_right =
+                    sum_right + T::cast_from((window * gain * gain_right) as f64) * frame_right;
+
+                grain.age += 1;
+                grain.position += grain.step;
+                grain.active = grain.age < grain.length && grain.position < buffer.len() as f64;
+            }
+            left[i] = sum_left;
+            right[i] = sum_right;
+        }
+
+        if self.density > 0. || self.active_grains() > 0 {
+            ProcessStatus::Running
+        } else {
+            ProcessStatus::Tail(0)
+        }
+    }
+}
+
+impl<T> GetParameter for Granular<T> {
+    type Param = GranularParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            GranularParams::Position => Value::Float(self.position),
+            GranularParams::Size => Value::Float(self.size),
+            GranularParams::Density => Value::Float(self.density),
+            GranularParams::Jitter => Value::Float(self.jitter),
+            GranularParams::Pitch => Value::Float(self.pitch),
+            GranularParams::Window => Value::Int(self.window.cast() as i64),
+            GranularParams::Spread => Value::Float(self.spread),
+        }
+    }
+}
+
+impl<T> SetParameter for Granular<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        match param {
+            GranularParams::Position => {
+                if let Ok(position) = f32::try_from(value) {
+                    self.set_position(position);
+                }
+            }
+            GranularParams::Size => {
+                if let Ok(size) = f32::try_from(value) {
+                    self.set_size(size);
+                }
+            }
+            GranularParams::Density => {
+                if let Ok(density) = f32::try_from(value) {
+                    self.set_density(density);
+                }
+            }
+            GranularParams::Jitter => {
+                if let Ok(jitter) = f32::try_from(value) {
+                    self.set_jitter(jitter);
+                }
+            }
+            GranularParams::Pitch => {
+                if let Ok(pitch) = f32::try_from(value) {
+                    self.set_pitch(pitch);
+                }
+            }
+            GranularParams::Window => {
+                if let Ok(window) = i64::try_from(value) {
+                    self.window = from_index_clamped(window);
+                }
+            }
+            GranularParams::Spread => {
+                if let Ok(spread) = f32::try_from(value) {
+                    self.set_spread(spread);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    const STREAM_DATA: StreamData = StreamData::new(64., 120., 64);
+
+    fn granular<T: 'static + Send + Sync + Float + CastFrom<f64> + Cast<usize>>(
+        samples: Vec<T>,
+    ) -> Granular<T> {
+        let slot = BufferSlot::new();
+        slot.store(SampleBuffer::from_channels(64., vec![samples]).unwrap());
+        let mut granular = Granular::new(slot, 1);
+        granular.reallocate(STREAM_DATA);
+        granular
+    }
+
+    fn process<T: 'static + Send + Sync + Float + CastFrom<f64> + Cast<usize>>(
+        granular: &mut Granular<T>,
+    ) -> (ProcessStatus, [[T; 64]; 2]) {
+        let mut outputs = [[T::zero(); 64]; 2];
+        let [left, right] = &mut outputs;
+        let status = granular.process(&STREAM_DATA, &[], &mut [left, right]);
+        (status, outputs)
+    }
+
+    #[rstest]
+    #[case(GrainWindow::Hann, [0., 0.5, 1., 0.5])]
+    #[case(GrainWindow::Triangle, [0., 0.5, 1., 0.5])]
+    #[case(GrainWindow::Rectangle, [1.; 4])]
+    fn test_window(#[case] window: GrainWindow, #[case] expected: [f32; 4]) {
+        let values = [0., 0.25, 0.5, 0.75].map(|x| window.value(x));
+        for (expected, actual) in expected.into_iter().zip(values) {
+            assert_relative_eq!(expected, actual, epsilon = 1e-6);
+        }
+    }
+
+    #[rstest]
+    fn test_back_to_back_grains() {
+        let mut granular = granular(vec![1f32; 128]);
+        granular.set_window(GrainWindow::Rectangle);
+        granular.set_size(0.125);
+        granular.set_density(8.);
+        let (status, [left, right]) = process(&mut granular);
+        assert_eq!(ProcessStatus::Running, status);
+        for (left, right) in left.into_iter().zip(right) {
+            assert_relative_eq!(FRAC_1_SQRT_2, left, epsilon = 1e-6);
+            assert_relative_eq!(FRAC_1_SQRT_2, right, epsilon = 1e-6);
+        }
+    }
+
+    #[rstest]
+    fn test_grain_pool_is_bounded() {
+        let mut granular = granular(vec![1f32; 128]);
+        granular.set_size(Granular::<f32>::MAX_SIZE);
+        granular.set_density(Granular::<f32>::MAX_DENSITY);
+        granular.set_spread(1.);
+        let (_, outputs) = process(&mut granular);
+        assert!(outputs.iter().flatten().all(|x| x.is_finite()));
+        assert_eq!(MAX_GRAINS, granular.active_grains());
+
+        // Spawns are dropped while the pool is full, instead of restarting playing grains
+        let ages = granular.grains.map(|grain| grain.age);
+        let stream_data = StreamData::new(64., 120., 1);
+        granular.process(&stream_data, &[], &mut [&mut [0.], &mut [0.]]);
+        assert_eq!(MAX_GRAINS, granular.active_grains());
+        assert_eq!(ages.map(|age| age + 1), granular.grains.map(|grain| grain.age));
+    }
+
+    #[rstest]
+    fn test_jitter_keeps_density() {
+        // Grains last 2 samples, so that processing one sample at a time leaves only the grain
+        // spawned during that sample active
+        let stream_data = StreamData::new(1000., 120., 1);
+        let slot = BufferSlot::new();
+        slot.store(SampleBuffer::from_channels(1000., vec![vec![1f32; 1000]]).unwrap());
+        let mut granular = Granular::new(slot, 1);
+        granular.reallocate(stream_data);
+        granular.set_position(0.5);
+        granular.set_size(0.002);
+        granular.set_density(100.);
+        granular.set_jitter(1.);
+
+        let mut spawned = 0;
+        for _ in 0..10_000 {
+            granular.process(&stream_data, &[], &mut [&mut [0.], &mut [0.]]);
+            spawned += granular.active_grains();
+        }
+        let per_second = spawned as f32 / 10.;
+        assert_relative_eq!(100., per_second, max_relative = 0.05);
+    }
+
+    #[rstest]
+    #[case(12., 12.)]
+    #[case(1000., Granular::<f32>::MAX_PITCH)]
+    #[case(-1000., -Granular::<f32>::MAX_PITCH)]
+    fn test_pitch_is_clamped(#[case] pitch: f32, #[case] expected: f32) {
+        let mut granular = Granular::<f32>::new(BufferSlot::new(), 1);
+        granular.set_param(GranularParams::Pitch, pitch);
+        assert_eq!(
+            Value::Float(expected),
+            granular.get_param_raw(GranularParams::Pitch)
+        );
+    }
+
+    #[rstest]
+    fn test_no_buffer_is_silent() {
+        let mut granular = Granular::<f32>::new(BufferSlot::new(), 1);
+        let (status, outputs) = process(&mut granular);
+        assert_eq!(ProcessStatus::Tail(0), status);
+        assert!(outputs.iter().flatten().all(|&x| x == 0.));
+    }
+
+    #[rstest]
+    fn test_f64_processing() {
+        let mut granular = granular(vec![1f64; 128]);
+        granular.set_window(GrainWindow::Rectangle);
+        granular.set_size(0.125);
+        granular.set_density(8.);
+        let (_, [left, right]) = process(&mut granular);
+        for (left, right) in left.into_iter().zip(right) {
+            assert_relative_eq!(std::f64::consts::FRAC_1_SQRT_2, left, epsilon = 1e-6);
+            assert_relative_eq!(std::f64::consts::FRAC_1_SQRT_2, right, epsilon = 1e-6);
+        }
+    }
+}
@@ -0,0 +1,17 @@
+#![warn(missing_docs)]
+//! Implementation of time-based and spectral audio effects, sample playback, granular synthesis
+//! and sequencing.
+//!
+//! This crate provides effect modules built on top of the `clogbox-core` primitives, such as delay
+//! lines, which can be used standalone or as building blocks for larger effects.
+
+pub mod chorus;
+pub mod convolution;
+pub mod delay;
+pub mod dynamics;
+pub mod granular;
+pub mod reverb;
+pub mod sampler;
+pub mod sequencer;
+pub mod slot;
+pub mod spectral;
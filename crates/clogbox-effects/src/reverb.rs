@@ -0,0 +1,404 @@
+//! Feedback delay network reverb.
+//!
+//! This module provides [`FdnReverb`], a stereo reverb made of a series of allpass diffusers
+//! feeding eight delay lines, which are mixed back into each other through a Householder matrix.
+//! Each line is damped by a one-pole lowpass filter, and its feedback gain is computed so that the
+//! reverb decays by 60 dB over the configured decay time.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, ProcessStatus, StreamData};
+//! use clogbox_effects::reverb::FdnReverb;
+//!
+//! let stream_data = StreamData::new(44100.0, 120.0, 64);
+//! let mut reverb = FdnReverb::<f32>::new(stream_data.sample_rate);
+//! reverb.set_decay(1.5);
+//!
+//! let (left, right) = ([0.0; 64], [0.0; 64]);
+//! let (mut out_left, mut out_right) = ([0.0; 64], [0.0; 64]);
+//! let status = reverb.process(&stream_data, &[&left, &right], &mut [&mut out_left, &mut out_right]);
+//! assert!(matches!(status, ProcessStatus::Tail(_)));
+//! ```
+use crate::delay::DelayBuffer;
+use az::CastFrom;
+use clogbox_core::module::stereo::{decode_mid_side, encode_mid_side, Stereo};
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::param::value::Value;
+use clogbox_core::param::{GetParameter, SetParameter};
+use clogbox_core::r#enum::enum_iter;
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_derive::Enum;
+use num_traits::Float;
+use numeric_literals::replace_float_literals;
+use std::array;
+
+/// Number of delay lines in the feedback network.
+const LINES: usize = 8;
+
+/// Lengths of the feedback delay lines at full size, in seconds.
+const LINE_LENGTHS: [f64; LINES] = [
+    0.0297, 0.0371, 0.0411, 0.0437, 0.0533, 0.0599, 0.0671, 0.0733,
+];
+
+/// Lengths of the input allpass diffusers, in seconds.
+const DIFFUSER_LENGTHS: [f64; 4] = [0.004771, 0.003595, 0.012730, 0.009307];
+
+/// Parameters of the [`FdnReverb`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum ReverbParams {
+    /// Room size, in 0..1, scaling the length of the delay lines.
+    Size,
+    /// Time for the reverb to decay by 60 dB, in seconds.
+    Decay,
+    /// High frequency damping, in 0..1.
+    Damping,
+    /// Amount of input diffusion, in 0..1.
+    Diffusion,
+    /// Stereo width of the reverberated signal, in 0..1.
+    Width,
+    /// Dry/wet mix, in 0..1.
+    Mix,
+}
+
+/// Schroeder allpass filter, used to diffuse the input of the reverb.
+#[derive(Debug, Clone)]
+struct Allpass<T> {
+    buffer: DelayBuffer<T>,
+}
+
+impl<T: Float> Allpass<T> {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: DelayBuffer::new(length),
+        }
+    }
+
+    #[inline]
+    fn process(&mut self, x: T, gain: T) -> T {
+        let delayed = self.buffer.get(self.buffer.capacity() - 1);
+        let w = x + gain * delayed;
+        self.buffer.push(w);
+        delayed - gain * w
+    }
+}
+
+/// A stereo feedback delay network reverb.
+#[derive(Debug, Clone)]
+pub struct FdnReverb<T> {
+    sample_rate: f64,
+    lines: [DelayBuffer<T>; LINES],
+    lengths: [usize; LINES],
+    gains: [T; LINES],
+    damping_state: [T; LINES],
+    diffusers: [Allpass<T>; 4],
+    size: f32,
+    decay: f32,
+    damping: f32,
+    diffusion: f32,
+    width: f32,
+    mix: f32,
+}
+
+impl<T: Float + CastFrom<f64>> FdnReverb<T> {
+    /// Creates a new reverb for the given sample rate, with a medium-sized room and a 2 second
+    /// decay.
+    pub fn new(sample_rate: f64) -> Self {
+        let mut this = Self {
+            sample_rate,
+            lines: array::from_fn(|i| {
+                DelayBuffer::new(seconds_to_samples(sample_rate, LINE_LENGTHS[i]))
+            }),
+            lengths: [1; LINES],
+            gains: [T::zero(); LINES],
+            damping_state: [T::zero(); LINES],
+            diffusers: array::from_fn(|i| {
+                Allpass::new(seconds_to_samples(sample_rate, DIFFUSER_LENGTHS[i]))
+            }),
+            size: 0.5,
+            decay: 2.,
+            damping: 0.5,
+            diffusion: 0.7,
+            width: 1.,
+            mix: 0.3,
+        };
+        this.update_lines();
+        this
+    }
+
+    /// Sets the room size, clamped to 0..1.
+    pub fn set_size(&mut self, size: f32) {
+        self.size = size.clamp(0., 1.);
+        self.update_lines();
+    }
+
+    /// Sets the time for the reverb to decay by 60 dB, in seconds.
+    pub fn set_decay(&mut self, decay: f32) {
+        self.decay = decay.max(0.);
+        self.update_lines();
+    }
+
+    /// Sets the high frequency damping, clamped to 0..1.
+    pub fn set_damping(&mut self, damping: f32) {
+        self.damping = damping.clamp(0., 1.);
+    }
+
+    /// Sets the amount of input diffusion, clamped to 0..1.
+    pub fn set_diffusion(&mut self, diffusion: f32) {
+        self.diffusion = diffusion.clamp(0., 1.);
+    }
+
+    /// Sets the stereo width of the reverberated signal, clamped to 0..1.
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width.clamp(0., 1.);
+    }
+
+    /// Sets the dry/wet mix, clamped to 0..1.
+    pub fn set_mix(&mut self, mix: f32) {
+        self.mix = mix.clamp(0., 1.);
+    }
+
+    /// Returns the number of samples the reverb takes to decay to silence after the input stops.
+    pub fn tail_samples(&self) -> u64 {
+        let longest = self.lengths.iter().copied().max().unwrap_or(0);
+        (self.decay as f64 * self.sample_rate).ceil() as u64 + longest as u64
+    }
+
+    /// Recomputes the delay line lengths and feedback gains from the size and decay parameters.
+    fn update_lines(&mut self) {
+        let scale = 0.25 + 0.75 * self.size as f64;
+        let decay_samples = self.decay as f64 * self.sample_rate;
+        for (i, &line_length) in LINE_LENGTHS.iter().enumerate() {
+            let length = seconds_to_samples(self.sample_rate, scale * line_length)
+                .min(self.lines[i].capacity());
+            self.lengths[i] = length;
+            // Gain for the line to decay by 60 dB over the decay time
+            self.gains[i] = if decay_samples > 0. {
+                T::cast_from(10f64.powf(-3. * length as f64 / decay_samples))
+            } else {
+                T::zero()
+            };
+        }
+    }
+}
+
+fn seconds_to_samples(sample_rate: f64, seconds: f64) -> usize {
+    (seconds * sample_rate).round().max(1.) as usize
+}
+
+impl<T: 'static + Send + Float + CastFrom<f64>> Module for FdnReverb<T> {
+    type Sample = T;
+    type Inputs = Stereo;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        if stream_data.sample_rate != self.sample_rate {
+            let Self {
+                size,
+                decay,
+                damping,
+                diffusion,
+                width,
+                mix,
+                ..
+            } = *self;
+            *self = Self {
+                size,
+                decay,
+                damping,
+                diffusion,
+                width,
+                mix,
+                ..Self::new(stream_data.sample_rate)
+            };
+            self.update_lines();
+        }
+    }
+
+    fn reset(&mut self) {
+        for line in &mut self.lines {
+            line.clear();
+        }
+        for diffuser in &mut self.diffusers {
+            diffuser.buffer.clear();
+        }
+        self.damping_state = [T::zero(); LINES];
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let latency = enum_iter::<Stereo>()
+            .map(|channel| input_latencies[channel])
+            .fold(0., f64::max);
+        EnumMapArray::new(|_| latency)
+    }
+
+    #[profiling::function]
+    #[replace_float_literals(T::cast_from(literal))]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let diffusion = 0.7 * T::cast_from(self.diffusion as f64);
+        let damping = 1.0 - 0.9 * T::cast_from(self.damping as f64);
+        let width = T::cast_from(self.width as f64);
+        let wet = T::cast_from(self.mix as f64);
+        let dry = 1.0 - wet;
+        let lines = T::cast_from(LINES as f64);
+        let input_gain = lines.sqrt().recip();
+        let output_gain = (lines / 2.0).sqrt().recip();
+        let householder = 2.0 / lines;
+
+        let (out_left, out_right) = outputs.split_at_mut(1);
+        let samples = out_left[0]
+            .iter_mut()
+            .zip(out_right[0].iter_mut())
+            .zip(inputs[0].iter().zip(inputs[1]));
+        for ((out_l, out_r), (&left, &right)) in samples {
+            let (mid, side) = encode_mid_side(left, right);
+            let mid = self
+                .diffusers
+                .iter_mut()
+                .fold(mid, |x, diffuser| diffuser.process(x, diffusion));
+
+            let delayed: [T; LINES] = array::from_fn(|i| self.lines[i].get(self.lengths[i] - 1));
+            for (state, &x) in self.damping_state.iter_mut().zip(&delayed) {
+                *state = *state + damping * (x - *state);
+            }
+            let feedback: [T; LINES] = array::from_fn(|i| self.gains[i] * self.damping_state[i]);
+            let sum = feedback.iter().fold(T::zero(), |acc, &x| acc + x) * householder;
+
+            let (mut wet_l, mut wet_r) = (T::zero(), T::zero());
+            for (i, line) in self.lines.iter_mut().enumerate() {
+                let (input, out) = if i % 2 == 0 {
+                    (mid + side, &mut wet_l)
+                } else {
+                    (mid - side, &mut wet_r)
+                };
+                line.push(input_gain * input + feedback[i] - sum);
+                *out = *out + delayed[i];
+            }
+
+            let (wet_mid, wet_side) = encode_mid_side(output_gain * wet_l, output_gain * wet_r);
+            let (wet_l, wet_r) = decode_mid_side(wet_mid, width * wet_side);
+            *out_l = dry * left + wet * wet_l;
+            *out_r = dry * right + wet * wet_r;
+        }
+        ProcessStatus::Tail(self.tail_samples())
+    }
+}
+
+impl<T> GetParameter for FdnReverb<T> {
+    type Param = ReverbParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        Value::Float(match param {
+            ReverbParams::Size => self.size,
+            ReverbParams::Decay => self.decay,
+            ReverbParams::Damping => self.damping,
+            ReverbParams::Diffusion => self.diffusion,
+            ReverbParams::Width => self.width,
+            ReverbParams::Mix => self.mix,
+        })
+    }
+}
+
+impl<T: Float + CastFrom<f64>> SetParameter for FdnReverb<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        let Ok(value) = f32::try_from(value) else {
+            return;
+        };
+        match param {
+            ReverbParams::Size => self.set_size(value),
+            ReverbParams::Decay => self.set_decay(value),
+            ReverbParams::Damping => self.set_damping(value),
+            ReverbParams::Diffusion => self.set_diffusion(value),
+            ReverbParams::Width => self.set_width(value),
+            ReverbParams::Mix => self.set_mix(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    const STREAM_DATA: StreamData = StreamData::new(8000., 120., 4000);
+
+    fn impulse_response(reverb: &mut FdnReverb<f64>) -> [Vec<f64>; 2] {
+        let mut left = vec![0.; STREAM_DATA.block_size];
+        let right = left.clone();
+        left[0] = 1.;
+        let mut outputs = [
+            vec![0.; STREAM_DATA.block_size],
+            vec![0.; STREAM_DATA.block_size],
+        ];
+        let [out_left, out_right] = &mut outputs;
+        reverb.process(&STREAM_DATA, &[&left, &right], &mut [out_left, out_right]);
+        outputs
+    }
+
+    fn energy(samples: &[f64]) -> f64 {
+        samples.iter().map(|x| x * x).sum()
+    }
+
+    #[rstest]
+    fn test_impulse_response_decays() {
+        let mut reverb = FdnReverb::new(STREAM_DATA.sample_rate);
+        reverb.set_mix(1.);
+        reverb.set_decay(0.2);
+        let [left, right] = impulse_response(&mut reverb);
+        let early = energy(&left[..1600]) + energy(&right[..1600]);
+        let late = energy(&left[2400..]) + energy(&right[2400..]);
+        assert!(early > 0.);
+        assert!(
+            late < early * 1e-4,
+            "Reverb did not decay: {late} >= {early} * 1e-4"
+        );
+    }
+
+    #[rstest]
+    fn test_zero_width_is_mono() {
+        let mut reverb = FdnReverb::new(STREAM_DATA.sample_rate);
+        reverb.set_mix(1.);
+        reverb.set_width(0.);
+        let [left, right] = impulse_response(&mut reverb);
+        assert!(energy(&left) > 0.);
+        assert_eq!(left, right);
+    }
+
+    #[rstest]
+    fn test_tail_follows_decay() {
+        let mut reverb = FdnReverb::<f64>::new(STREAM_DATA.sample_rate);
+        reverb.set_param(ReverbParams::Size, 1.0f32);
+        reverb.set_param(ReverbParams::Decay, 1.0f32);
+        assert_eq!(8000 + 586, reverb.tail_samples());
+    }
+
+    #[rstest]
+    fn test_reset_clears_state() {
+        let mut reverb = FdnReverb::new(STREAM_DATA.sample_rate);
+        impulse_response(&mut reverb);
+        assert!(reverb.damping_state.iter().any(|x| *x != 0.));
+        reverb.reset();
+        assert!(reverb.damping_state.iter().all(|x| *x == 0.));
+        let silence = vec![0.; STREAM_DATA.block_size];
+        let mut outputs = [silence.clone(), silence.clone()];
+        let [out_left, out_right] = &mut outputs;
+        reverb.process(
+            &STREAM_DATA,
+            &[&silence, &silence],
+            &mut [out_left, out_right],
+        );
+        assert_eq!([silence.clone(), silence], outputs);
+    }
+}
@@ -0,0 +1,530 @@
+//! Sample playback.
+//!
+//! This module provides [`SampleBuffer`], an immutable multichannel audio buffer which can be
+//! loaded from WAV files, [`BufferSlot`], which hands buffers over to the audio thread in a
+//! real-time safe way, and [`Sampler`], a module playing back the buffer held by a slot.
+//!
+//! Buffers are shared through [`Arc`]s: the audio thread only ever swaps pointers, and the buffers
+//! it stops using are handed back to the slot, so that they are deallocated outside the audio
+//! thread (see the [`slot`](crate::slot) module).
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_effects::sampler::{BufferSlot, SampleBuffer, Sampler};
+//!
+//! let stream_data = StreamData::new(44100.0, 120.0, 4);
+//! let slot = BufferSlot::new();
+//! slot.store(SampleBuffer::from_channels(44100.0, vec![vec![1.0, 0.5, 0.25, 0.0]]).unwrap());
+//!
+//! let mut sampler = Sampler::<f32>::new(slot.clone());
+//! let gate = [1.0; 4];
+//! let note = [60.0; 4];
+//! let (mut left, mut right) = ([0.0; 4], [0.0; 4]);
+//! sampler.process(&stream_data, &[&gate, &note], &mut [&mut left, &mut right]);
+//! assert_eq!([1.0, 0.5, 0.25, 0.0], left);
+//! ```
+use crate::slot::Slot;
+use az::{Cast, CastFrom};
+use clogbox_core::math::interpolation::{Cubic, Interpolation};
+use clogbox_core::module::stereo::Stereo;
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::param::value::Value;
+use clogbox_core::param::{GetParameter, SetParameter};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::from_index_clamped;
+use clogbox_derive::Enum;
+use num_traits::Float;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Errors which can occur when creating or loading a [`SampleBuffer`].
+#[derive(Debug, Error)]
+pub enum SampleBufferError {
+    /// The buffer has no channels.
+    #[error("Sample buffer has no channels")]
+    NoChannels,
+    /// The channels of the buffer have different lengths.
+    #[error("Sample buffer channels have different lengths")]
+    MismatchedChannels,
+    /// The WAV file could not be read.
+    #[error("Cannot read WAV file: {0}")]
+    Wav(#[from] hound::Error),
+}
+
+/// An immutable multichannel audio buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleBuffer<T> {
+    channels: Box<[Box<[T]>]>,
+    sample_rate: f64,
+}
+
+impl<T> SampleBuffer<T> {
+    /// Creates a new buffer from its channels, which must all have the same length.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Sample rate at which the buffer was recorded.
+    /// * `channels` - Samples of each channel of the buffer.
+    pub fn from_channels(
+        sample_rate: f64,
+        channels: Vec<Vec<T>>,
+    ) -> Result<Self, SampleBufferError> {
+        let Some(len) = channels.first().map(Vec::len) else {
+            return Err(SampleBufferError::NoChannels);
+        };
+        if channels.iter().any(|channel| channel.len() != len) {
+            return Err(SampleBufferError::MismatchedChannels);
+        }
+        Ok(Self {
+            channels: channels.into_iter().map(Vec::into_boxed_slice).collect(),
+            sample_rate,
+        })
+    }
+
+    /// Returns the sample rate at which the buffer was recorded.
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Returns the number of channels of the buffer.
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Returns the length of the buffer, in frames.
+    pub fn len(&self) -> usize {
+        self.channels[0].len()
+    }
+
+    /// Returns whether the buffer holds no frames.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the samples of the given channel.
+    pub fn channel(&self, index: usize) -> &[T] {
+        &self.channels[index]
+    }
+}
+
+impl<T: Float + CastFrom<f64> + Cast<usize>> SampleBuffer<T> {
+    /// Reads a stereo frame at a fractional position (in frames), with cubic interpolation.
+    ///
+    /// Mono buffers are played back on both channels, and buffers with more than 2 channels only
+    /// have their first two channels read.
+    pub fn frame(&self, position: f64) -> (T, T) {
+        let read = |channel: &[T]| {
+            let last = channel.len() as isize - 1;
+            let i = position.floor() as isize;
+            let window = [-1, 0, 1, 2].map(|k| channel[(i + k).clamp(0, last) as usize]);
+            Cubic.interpolate(
+                &window.as_slice(),
+                T::one() + T::cast_from(position.fract()),
+            )
+        };
+        let left = read(&self.channels[0]);
+        let right = self.channels.get(1).map_or(left, |channel| read(channel));
+        (left, right)
+    }
+}
+
+impl<T: CastFrom<f64>> SampleBuffer<T> {
+    /// Loads a buffer from a WAV file. This allocates, and must not be called from the audio
+    /// thread.
+    pub fn load_wav(path: impl AsRef<Path>) -> Result<Self, SampleBufferError> {
+        Self::read_wav(io::BufReader::new(
+            std::fs::File::open(path).map_err(hound::Error::from)?,
+        ))
+    }
+
+    /// Reads a buffer from WAV data. Integer samples are normalized to the -1..1 range.
+    pub fn read_wav(reader: impl io::Read) -> Result<Self, SampleBufferError> {
+        let reader = hound::WavReader::new(reader)?;
+        let spec = reader.spec();
+        let samples = match spec.sample_format {
+            hound::SampleFormat::Float => reader
+                .into_samples::<f32>()
+                .map(|s| s.map(f64::from))
+                .collect::<Result<Vec<_>, _>>()?,
+            hound::SampleFormat::Int => {
+                let scale = (1u64 << (spec.bits_per_sample - 1)) as f64;
+                reader
+                    .into_samples::<i32>()
+                    .map(|s| s.map(|s| s as f64 / scale))
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+        };
+        let num_channels = spec.channels as usize;
+        let channels = (0..num_channels)
+            .map(|c| {
+                samples
+                    .iter()
+                    .skip(c)
+                    .step_by(num_channels)
+                    .map(|&s| T::cast_from(s))
+                    .collect()
+            })
+            .collect();
+        Self::from_channels(spec.sample_rate as f64, channels)
+    }
+}
+
+/// A shared slot handing [`SampleBuffer`]s over to modules running on the audio thread.
+pub type BufferSlot<T> = Slot<SampleBuffer<T>>;
+
+/// Playback mode of a [`Sampler`].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum PlaybackMode {
+    /// Plays the region once, until its end, every time the gate opens.
+    #[default]
+    #[display = "One-shot"]
+    OneShot,
+    /// Loops the region for as long as the gate is open.
+    Loop,
+}
+
+/// Inputs of the [`Sampler`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum SamplerInput {
+    /// Gate signal; playback starts when it goes above 0.5.
+    Gate,
+    /// Note to play, as a (possibly fractional) MIDI note number.
+    Note,
+}
+
+/// Parameters of the [`Sampler`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum SamplerParams {
+    /// Playback mode, as the index of a [`PlaybackMode`].
+    Mode,
+    /// MIDI note at which the buffer plays at its original pitch.
+    #[display = "Root key"]
+    RootKey,
+    /// Start of the played region, relative to the length of the buffer (0..1).
+    Start,
+    /// End of the played region, relative to the length of the buffer (0..1).
+    End,
+}
+
+/// A module playing back the buffer held by a [`BufferSlot`], transposed by its note input.
+#[derive(Debug)]
+pub struct Sampler<T> {
+    slot: BufferSlot<T>,
+    buffer: Option<Arc<SampleBuffer<T>>>,
+    mode: PlaybackMode,
+    root_key: f32,
+    start: f32,
+    end: f32,
+    position: f64,
+    playing: bool,
+    gate: bool,
+    sample_rate: f64,
+}
+
+impl<T> Sampler<T> {
+    /// Creates a new sampler playing the buffers stored in the given slot.
+    ///
+    /// The sampler defaults to one-shot playback of the whole buffer, with a root key of 60
+    /// (middle C).
+    pub fn new(slot: BufferSlot<T>) -> Self {
+        Self {
+            slot,
+            buffer: None,
+            mode: PlaybackMode::default(),
+            root_key: 60.,
+            start: 0.,
+            end: 1.,
+            position: 0.,
+            playing: false,
+            gate: false,
+            sample_rate: 44100.,
+        }
+    }
+
+    /// Sets the playback mode.
+    pub fn set_mode(&mut self, mode: PlaybackMode) {
+        self.mode = mode;
+    }
+
+    /// Sets the MIDI note at which the buffer plays at its original pitch.
+    pub fn set_root_key(&mut self, root_key: f32) {
+        self.root_key = root_key;
+    }
+
+    /// Sets the played region, relative to the length of the buffer. Both ends are clamped to
+    /// 0..1, and swapped if `start` is after `end`.
+    pub fn set_region(&mut self, start: f32, end: f32) {
+        let (start, end) = (start.clamp(0., 1.), end.clamp(0., 1.));
+        (self.start, self.end) = (start.min(end), start.max(end));
+    }
+
+    /// Returns whether the sampler is currently playing.
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    fn region(&self, len: usize) -> (f64, f64) {
+        let len = len as f64;
+        (self.start as f64 * len, self.end as f64 * len)
+    }
+}
+
+impl<T: 'static + Send + Sync + Float + CastFrom<f64> + Cast<f64> + Cast<usize>> Module
+    for Sampler<T>
+{
+    type Sample = T;
+    type Inputs = SamplerInput;
+    type Outputs = Stereo;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.sample_rate = stream_data.sample_rate;
+    }
+
+    fn reset(&mut self) {
+        self.position = 0.;
+        self.playing = false;
+        self.gate = false;
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|_| input_latencies[SamplerInput::Gate])
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        self.slot.fetch(&mut self.buffer);
+        let (left, right) = outputs.split_at_mut(1);
+        let (left, right) = (&mut *left[0], &mut *right[0]);
+        let Some(buffer) = self.buffer.as_deref().filter(|buffer| !buffer.is_empty()) else {
+            left.fill(T::zero());
+            right.fill(T::zero());
+            return ProcessStatus::Tail(0);
+        };
+
+        let (start, end) = self.region(buffer.len());
+        let rate = buffer.sample_rate() / stream_data.sample_rate;
+        let gate = inputs[SamplerInput::Gate.cast()];
+        let note = inputs[SamplerInput::Note.cast()];
+        for i in 0..stream_data.block_size {
+            let gate = gate[i] > T::cast_from(0.5);
+            if gate && !self.gate {
+                self.position = start;
+                self.playing = end > start;
+            } else if !gate && self.mode == PlaybackMode::Loop {
+                self.playing = false;
+            }
+            self.gate = gate;
+
+            if !self.playing {
+                left[i] = T::zero();
+                right[i] = T::zero();
+                continue;
+            }
+            (left[i], right[i]) = buffer.frame(self.position);
+            let semitones = Cast::<f64>::cast(note[i]) - self.root_key as f64;
+            self.position += rate * (semitones / 12.).exp2();
+            if self.position >= end {
+                match self.mode {
+                    // The region can collapse while playing, when its ends are automated
+                    PlaybackMode::Loop if end > start => {
+                        self.position = start + (self.position - start) % (end - start)
+                    }
+                    _ => self.playing = false,
+                }
+            }
+        }
+
+        if self.playing {
+            ProcessStatus::Running
+        } else {
+            ProcessStatus::Tail(0)
+        }
+    }
+}
+
+impl<T> GetParameter for Sampler<T> {
+    type Param = SamplerParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            SamplerParams::Mode => Value::Int(self.mode.cast() as i64),
+            SamplerParams::RootKey => Value::Float(self.root_key),
+            SamplerParams::Start => Value::Float(self.start),
+            SamplerParams::End => Value::Float(self.end),
+        }
+    }
+}
+
+impl<T> SetParameter for Sampler<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        match param {
+            SamplerParams::Mode => {
+                if let Ok(mode) = i64::try_from(value) {
+                    self.mode = from_index_clamped(mode);
+                }
+            }
+            SamplerParams::RootKey => {
+                if let Ok(root_key) = f32::try_from(value) {
+                    self.root_key = root_key;
+                }
+            }
+            SamplerParams::Start => {
+                if let Ok(start) = f32::try_from(value) {
+                    self.set_region(start, self.end);
+                }
+            }
+            SamplerParams::End => {
+                if let Ok(end) = f32::try_from(value) {
+                    self.set_region(self.start, end);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    const STREAM_DATA: StreamData = StreamData::new(8., 120., 8);
+
+    fn sampler(samples: &[f32]) -> (BufferSlot<f32>, Sampler<f32>) {
+        let slot = BufferSlot::new();
+        slot.store(SampleBuffer::from_channels(8., vec![samples.to_vec()]).unwrap());
+        let sampler = Sampler::new(slot.clone());
+        (slot, sampler)
+    }
+
+    fn process(sampler: &mut Sampler<f32>, gate: [f32; 8], note: f32) -> [f32; 8] {
+        let (mut left, mut right) = ([0.; 8], [0.; 8]);
+        sampler.process(
+            &STREAM_DATA,
+            &[&gate, &[note; 8]],
+            &mut [&mut left, &mut right],
+        );
+        assert_eq!(left, right);
+        left
+    }
+
+    #[rstest]
+    #[case(60., [1., 2., 3., 4., 0., 0., 0., 0.])]
+    #[case(72., [1., 3., 0., 0., 0., 0., 0., 0.])]
+    fn test_one_shot(#[case] note: f32, #[case] expected: [f32; 8]) {
+        let (_, mut sampler) = sampler(&[1., 2., 3., 4.]);
+        assert_eq!(expected, process(&mut sampler, [1.; 8], note));
+        assert!(!sampler.is_playing());
+    }
+
+    #[rstest]
+    fn test_fractional_note() {
+        let (_, mut sampler) = sampler(&[1., 2., 3., 4.]);
+        // Plays the buffer 1.5 times faster
+        let note = 60. + 12. * 1.5f32.log2();
+        let output = process(&mut sampler, [1.; 8], note);
+        for (expected, actual) in [1., 2.5, 4., 0.].into_iter().zip(output) {
+            assert_relative_eq!(expected, actual, epsilon = 1e-4);
+        }
+    }
+
+    #[rstest]
+    fn test_negative_note() {
+        let (_, mut sampler) = sampler(&[1., 2., 3., 4.]);
+        let output = process(&mut sampler, [1.; 8], -60.);
+        assert!(sampler.is_playing());
+        assert!(output.iter().all(|x| (1. ..1.1).contains(x)));
+    }
+
+    #[rstest]
+    fn test_loop_region() {
+        let (_, mut sampler) = sampler(&[1., 2., 3., 4.]);
+        sampler.set_mode(PlaybackMode::Loop);
+        sampler.set_region(0.25, 0.75);
+        let gate = [1., 1., 1., 1., 1., 0., 0., 0.];
+        assert_eq!(
+            [2., 3., 2., 3., 2., 0., 0., 0.],
+            process(&mut sampler, gate, 60.)
+        );
+    }
+
+    #[rstest]
+    fn test_loop_region_collapses_while_playing() {
+        let (_, mut sampler) = sampler(&[1., 2., 3., 4.]);
+        sampler.set_mode(PlaybackMode::Loop);
+        process(&mut sampler, [1.; 8], 60.);
+        sampler.set_param(SamplerParams::Start, 0.5f32);
+        sampler.set_param(SamplerParams::End, 0.5f32);
+        // Playback continues from the start of the buffer, and stops at the collapsed region
+        assert_eq!(
+            [1., 2., 0., 0., 0., 0., 0., 0.],
+            process(&mut sampler, [1.; 8], 60.)
+        );
+        assert!(!sampler.is_playing());
+    }
+
+    #[rstest]
+    fn test_retrigger_on_gate() {
+        let (_, mut sampler) = sampler(&[1., 2., 3.]);
+        let gate = [1., 1., 0., 1., 1., 1., 1., 1.];
+        assert_eq!(
+            [1., 2., 3., 1., 2., 3., 0., 0.],
+            process(&mut sampler, gate, 60.)
+        );
+    }
+
+    #[rstest]
+    fn test_buffer_swap() {
+        let (slot, mut sampler) = sampler(&[1.; 4]);
+        process(&mut sampler, [0.; 8], 60.);
+        slot.store(SampleBuffer::from_channels(8., vec![vec![2.; 4]]).unwrap());
+        assert_eq!(
+            [2., 2., 2., 2., 0., 0., 0., 0.],
+            process(&mut sampler, [1.; 8], 60.)
+        );
+        let retired = slot.0.lock().unwrap().retired.clone().unwrap();
+        assert_eq!(&[1.; 4], retired.channel(0));
+        drop(retired);
+        slot.collect();
+        assert!(slot.0.lock().unwrap().retired.is_none());
+    }
+
+    #[rstest]
+    fn test_read_wav() {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 48000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut data = io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut data, spec).unwrap();
+        for sample in [16384i16, -16384, 0, 32767] {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        data.set_position(0);
+        let buffer = SampleBuffer::<f64>::read_wav(data).unwrap();
+        assert_eq!(48000., buffer.sample_rate());
+        assert_eq!(2, buffer.num_channels());
+        assert_eq!(&[0.5, 0.], buffer.channel(0));
+        assert_eq!(&[-0.5, 32767. / 32768.], buffer.channel(1));
+    }
+}
@@ -0,0 +1,559 @@
+//! Tempo-aware clock generation and step sequencing.
+//!
+//! This module provides [`Clock`], which generates a gate signal synchronized to the tempo of the
+//! stream, [`ClockDivider`], which only lets one in every N pulses of a clock signal through, and
+//! [`StepSequencer`], which steps through a sequence of notes on each clock pulse.
+//!
+//! Clock and gate signals are plain audio signals, which are high above 0.5 and low otherwise. The
+//! outputs of the [`StepSequencer`] can be fed directly to the inputs of a
+//! [`Sampler`](crate::sampler::Sampler), making it possible to build generative patches out of
+//! modules.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_effects::sequencer::{Clock, Step, StepSequencer};
+//!
+//! let stream_data = StreamData::new(8.0, 60.0, 16);
+//! // One pulse per beat, that is every 8 samples
+//! let mut clock = Clock::<f32>::new(1.0);
+//! let mut sequencer = StepSequencer::<f32>::new(2);
+//! sequencer.set_step(0, Step::new(60.0));
+//! sequencer.set_step(1, Step::new(67.0));
+//!
+//! let mut pulses = [0.0; 16];
+//! clock.process(&stream_data, &[], &mut [&mut pulses]);
+//! let (mut gate, mut note) = ([0.0; 16], [0.0; 16]);
+//! sequencer.process(&stream_data, &[&pulses, &[0.0; 16]], &mut [&mut gate, &mut note]);
+//! assert_eq!(60.0, note[0]);
+//! assert_eq!(67.0, note[8]);
+//! ```
+use az::CastFrom;
+use clogbox_core::module::sample::SampleModule;
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::param::value::Value;
+use clogbox_core::param::{GetParameter, SetParameter};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::{seq, Empty, Sequential};
+use clogbox_derive::Enum;
+use num_traits::{Float, Zero};
+use std::marker::PhantomData;
+use typenum::U1;
+
+/// Maximum number of steps in a [`StepSequencer`].
+pub const MAX_STEPS: usize = 16;
+
+fn gate_value<T: Zero + CastFrom<f64>>(high: bool) -> T {
+    if high {
+        T::cast_from(1.)
+    } else {
+        T::zero()
+    }
+}
+
+/// Parameters of the [`Clock`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum ClockParams {
+    /// Length of a clock period, in beats.
+    Rate,
+    /// Portion of the clock period during which the output is high (0..1).
+    #[display = "Pulse width"]
+    PulseWidth,
+}
+
+/// A module generating a gate signal synchronized to the tempo of the stream.
+///
+/// The output is high at the start of each period, for a portion of the period given by the pulse
+/// width. While the host transport is playing, the clock is synchronized to the playhead, so that
+/// periods start on multiples of the rate; otherwise it runs freely.
+#[derive(Debug, Clone)]
+pub struct Clock<T> {
+    rate: f32,
+    pulse_width: f32,
+    phase: f64,
+    __sample: PhantomData<fn() -> T>,
+}
+
+impl<T> Clock<T> {
+    /// Creates a new clock with the given period, in beats, and a pulse width of 50%.
+    pub fn new(rate: f32) -> Self {
+        Self {
+            rate: rate.max(1e-3),
+            pulse_width: 0.5,
+            phase: 0.,
+            __sample: PhantomData,
+        }
+    }
+
+    /// Returns the length of a clock period, in beats.
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Sets the length of a clock period, in beats.
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(1e-3);
+    }
+
+    /// Sets the portion of the clock period during which the output is high, clamped to 0..1.
+    pub fn set_pulse_width(&mut self, pulse_width: f32) {
+        self.pulse_width = pulse_width.clamp(0., 1.);
+    }
+}
+
+impl<T: 'static + Send + Copy + Zero + CastFrom<f64>> Module for Clock<T> {
+    type Sample = T;
+    type Inputs = Empty;
+    type Outputs = Sequential<U1>;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.phase = 0.;
+    }
+
+    fn latency(&self, _: EnumMapArray<Self::Inputs, f64>) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|_| 0.)
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        stream_data: &StreamData,
+        _: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let step = stream_data.beat_sample_length(self.rate as f64).recip();
+        if stream_data.transport.playing {
+            self.phase = (stream_data.transport.position / self.rate as f64).rem_euclid(1.);
+        }
+        for out in &mut outputs[0][..stream_data.block_size] {
+            *out = gate_value(self.phase < self.pulse_width as f64);
+            self.phase = (self.phase + step).fract();
+        }
+        ProcessStatus::Running
+    }
+}
+
+impl<T> GetParameter for Clock<T> {
+    type Param = ClockParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            ClockParams::Rate => Value::Float(self.rate),
+            ClockParams::PulseWidth => Value::Float(self.pulse_width),
+        }
+    }
+}
+
+impl<T> SetParameter for Clock<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        let Ok(value) = f32::try_from(value) else {
+            return;
+        };
+        match param {
+            ClockParams::Rate => self.set_rate(value),
+            ClockParams::PulseWidth => self.set_pulse_width(value),
+        }
+    }
+}
+
+/// Parameters of the [`ClockDivider`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum ClockDividerParams {
+    /// Number of input pulses per output pulse.
+    Division,
+}
+
+/// A module letting one in every N pulses of its clock input through.
+///
+/// The first pulse after a reset is always let through.
+#[derive(Debug, Clone)]
+pub struct ClockDivider<T> {
+    division: u32,
+    count: u32,
+    input: bool,
+    output: bool,
+    __sample: PhantomData<fn() -> T>,
+}
+
+impl<T> ClockDivider<T> {
+    /// Creates a new clock divider with the given division, which is at least 1.
+    pub fn new(division: u32) -> Self {
+        Self {
+            division: division.max(1),
+            count: 0,
+            input: false,
+            output: false,
+            __sample: PhantomData,
+        }
+    }
+
+    /// Returns the number of input pulses per output pulse.
+    pub fn division(&self) -> u32 {
+        self.division
+    }
+
+    /// Sets the number of input pulses per output pulse, which is at least 1.
+    pub fn set_division(&mut self, division: u32) {
+        self.division = division.max(1);
+        self.count %= self.division;
+    }
+}
+
+impl<T: 'static + Send + Float + CastFrom<f64>> SampleModule for ClockDivider<T> {
+    type Sample = T;
+    type Inputs = Sequential<U1>;
+    type Outputs = Sequential<U1>;
+
+    fn reset(&mut self) {
+        self.count = 0;
+        self.input = false;
+        self.output = false;
+    }
+
+    fn latency(
+        &self,
+        input_latency: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        input_latency
+    }
+
+    fn process_sample(
+        &mut self,
+        _: &StreamData,
+        inputs: EnumMapArray<Self::Inputs, Self::Sample>,
+    ) -> (ProcessStatus, EnumMapArray<Self::Outputs, Self::Sample>) {
+        let input = inputs[seq(0)] > T::cast_from(0.5);
+        if input && !self.input {
+            self.output = self.count == 0;
+            self.count = (self.count + 1) % self.division;
+        } else if !input {
+            self.output = false;
+        }
+        self.input = input;
+        (
+            ProcessStatus::Running,
+            EnumMapArray::new(|_| gate_value(self.output)),
+        )
+    }
+}
+
+impl<T> GetParameter for ClockDivider<T> {
+    type Param = ClockDividerParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            ClockDividerParams::Division => Value::Int(self.division as i64),
+        }
+    }
+}
+
+impl<T> SetParameter for ClockDivider<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        let Ok(value) = i64::try_from(value) else {
+            return;
+        };
+        match param {
+            ClockDividerParams::Division => {
+                self.set_division(value.clamp(1, u32::MAX as i64) as u32)
+            }
+        }
+    }
+}
+
+/// A single step of a [`StepSequencer`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Step {
+    /// Note of the step, as a (possibly fractional) MIDI note number.
+    pub note: f32,
+    /// Whether the step opens the gate; inactive steps are rests.
+    pub active: bool,
+}
+
+impl Step {
+    /// Creates a new active step playing the given note.
+    pub const fn new(note: f32) -> Self {
+        Self { note, active: true }
+    }
+
+    /// Creates a new rest, which keeps the gate closed.
+    pub const fn rest() -> Self {
+        Self {
+            note: 60.,
+            active: false,
+        }
+    }
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Self::new(60.)
+    }
+}
+
+/// Inputs of the [`StepSequencer`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum SequencerInput {
+    /// Clock signal; the sequencer advances by one step when it goes above 0.5.
+    Clock,
+    /// Reset signal; the next clock pulse plays the first step when it goes above 0.5.
+    Reset,
+}
+
+/// Outputs of the [`StepSequencer`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum SequencerOutput {
+    /// Gate signal, following the clock input on active steps.
+    Gate,
+    /// Note of the current step, as a MIDI note number.
+    Note,
+}
+
+/// Parameters of the [`StepSequencer`] module.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum SequencerParams {
+    /// Number of steps in the sequence, between 1 and [`MAX_STEPS`].
+    Length,
+}
+
+/// A module stepping through a sequence of up to [`MAX_STEPS`] notes, advancing on each pulse of
+/// its clock input.
+#[derive(Debug, Clone)]
+pub struct StepSequencer<T> {
+    steps: [Step; MAX_STEPS],
+    length: usize,
+    position: Option<usize>,
+    clock: bool,
+    reset: bool,
+    __sample: PhantomData<fn() -> T>,
+}
+
+impl<T> StepSequencer<T> {
+    /// Creates a new sequencer with the given number of steps, all playing middle C.
+    pub fn new(length: usize) -> Self {
+        Self {
+            steps: [Step::default(); MAX_STEPS],
+            length: length.clamp(1, MAX_STEPS),
+            position: None,
+            clock: false,
+            reset: false,
+            __sample: PhantomData,
+        }
+    }
+
+    /// Returns the number of steps in the sequence.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// Sets the number of steps in the sequence, clamped between 1 and [`MAX_STEPS`].
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length.clamp(1, MAX_STEPS);
+    }
+
+    /// Returns the step at the given index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is not less than [`MAX_STEPS`].
+    pub fn step(&self, index: usize) -> Step {
+        self.steps[index]
+    }
+
+    /// Sets the step at the given index. Steps past the length of the sequence are kept, and are
+    /// played again when the sequence is made longer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is not less than [`MAX_STEPS`].
+    pub fn set_step(&mut self, index: usize, step: Step) {
+        self.steps[index] = step;
+    }
+
+    /// Returns the index of the current step, or `None` if no clock pulse has been received since
+    /// the last reset.
+    pub fn position(&self) -> Option<usize> {
+        self.position
+    }
+}
+
+impl<T: 'static + Send + Float + CastFrom<f64>> SampleModule for StepSequencer<T> {
+    type Sample = T;
+    type Inputs = SequencerInput;
+    type Outputs = SequencerOutput;
+
+    fn reset(&mut self) {
+        self.position = None;
+        self.clock = false;
+        self.reset = false;
+    }
+
+    fn latency(
+        &self,
+        input_latency: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|_| input_latency[SequencerInput::Clock])
+    }
+
+    fn process_sample(
+        &mut self,
+        _: &StreamData,
+        inputs: EnumMapArray<Self::Inputs, Self::Sample>,
+    ) -> (ProcessStatus, EnumMapArray<Self::Outputs, Self::Sample>) {
+        let threshold = T::cast_from(0.5);
+        let reset = inputs[SequencerInput::Reset] > threshold;
+        if reset && !self.reset {
+            self.position = None;
+        }
+        self.reset = reset;
+
+        let clock = inputs[SequencerInput::Clock] > threshold;
+        if clock && !self.clock {
+            self.position = Some(self.position.map_or(0, |pos| (pos + 1) % self.length));
+        }
+        self.clock = clock;
+
+        let step = self.steps[self.position.unwrap_or(0).min(self.length - 1)];
+        let gate = clock && step.active && self.position.is_some();
+        (
+            ProcessStatus::Running,
+            EnumMapArray::new(|output| match output {
+                SequencerOutput::Gate => gate_value(gate),
+                SequencerOutput::Note => T::cast_from(step.note as f64),
+            }),
+        )
+    }
+}
+
+impl<T> GetParameter for StepSequencer<T> {
+    type Param = SequencerParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            SequencerParams::Length => Value::Int(self.length as i64),
+        }
+    }
+}
+
+impl<T> SetParameter for StepSequencer<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        let Ok(value) = i64::try_from(value) else {
+            return;
+        };
+        match param {
+            SequencerParams::Length => self.set_length(value.clamp(1, MAX_STEPS as i64) as usize),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clogbox_core::module::Transport;
+    use rstest::rstest;
+
+    const STREAM_DATA: StreamData = StreamData::new(8., 60., 16);
+
+    fn rising_edges(signal: &[f32]) -> Vec<usize> {
+        let mut previous = 0.;
+        let mut edges = vec![];
+        for (i, &x) in signal.iter().enumerate() {
+            if x > 0.5 && previous <= 0.5 {
+                edges.push(i);
+            }
+            previous = x;
+        }
+        edges
+    }
+
+    #[rstest]
+    #[case(1., 0.5, vec![0, 8])]
+    #[case(0.5, 0.25, vec![0, 4, 8, 12])]
+    fn test_clock_follows_tempo(
+        #[case] rate: f32,
+        #[case] pulse_width: f32,
+        #[case] expected: Vec<usize>,
+    ) {
+        let mut clock = Clock::<f32>::new(rate);
+        clock.set_param(ClockParams::PulseWidth, pulse_width);
+        let mut output = [0.; 16];
+        clock.process(&STREAM_DATA, &[], &mut [&mut output]);
+        assert_eq!(expected, rising_edges(&output));
+        let high = output.iter().filter(|&&x| x > 0.5).count();
+        assert_eq!((16. * pulse_width) as usize, high);
+    }
+
+    #[rstest]
+    fn test_clock_follows_transport() {
+        let mut clock = Clock::<f32>::new(1.);
+        let stream_data = StreamData {
+            transport: Transport {
+                playing: true,
+                position: 2.5,
+                ..Transport::STOPPED
+            },
+            ..STREAM_DATA
+        };
+        let mut output = [0.; 16];
+        clock.process(&stream_data, &[], &mut [&mut output]);
+        assert_eq!(vec![4, 12], rising_edges(&output));
+    }
+
+    #[rstest]
+    fn test_clock_divider() {
+        let mut clock = Clock::<f32>::new(0.25);
+        let mut divider = ClockDivider::<f32>::new(3);
+        let (mut pulses, mut output) = ([0.; 16], [0.; 16]);
+        clock.process(&STREAM_DATA, &[], &mut [&mut pulses]);
+        divider.process(&STREAM_DATA, &[&pulses], &mut [&mut output]);
+        assert_eq!(vec![0, 2, 4, 6, 8, 10, 12, 14], rising_edges(&pulses));
+        assert_eq!(vec![0, 6, 12], rising_edges(&output));
+        assert_eq!(
+            Value::Int(3),
+            divider.get_param_raw(ClockDividerParams::Division)
+        );
+    }
+
+    #[rstest]
+    fn test_sequencer_steps_and_rests() {
+        let mut sequencer = StepSequencer::<f64>::new(3);
+        sequencer.set_step(0, Step::new(60.));
+        sequencer.set_step(1, Step::rest());
+        sequencer.set_step(2, Step::new(64.));
+        let clock = [1., 0., 1., 0., 1., 0., 1., 0.];
+        let (mut gate, mut note) = ([0.; 8], [0.; 8]);
+        let stream_data = StreamData {
+            block_size: 8,
+            ..STREAM_DATA
+        };
+        sequencer.process(
+            &stream_data,
+            &[&clock, &[0.; 8]],
+            &mut [&mut gate, &mut note],
+        );
+        assert_eq!([1., 0., 0., 0., 1., 0., 1., 0.], gate);
+        assert_eq!([60., 60., 60., 60., 64., 64., 60., 60.], note);
+        assert_eq!(Some(0), sequencer.position());
+    }
+
+    #[rstest]
+    fn test_sequencer_reset() {
+        let mut sequencer = StepSequencer::<f32>::new(4);
+        for i in 0..4 {
+            sequencer.set_step(i, Step::new(60. + i as f32));
+        }
+        let clock = [1., 0., 1., 0., 1., 0., 1., 0.];
+        let reset = [0., 0., 0., 1., 0., 0., 0., 0.];
+        let (mut gate, mut note) = ([0.; 8], [0.; 8]);
+        let stream_data = StreamData {
+            block_size: 8,
+            ..STREAM_DATA
+        };
+        sequencer.process(&stream_data, &[&clock, &reset], &mut [&mut gate, &mut note]);
+        assert_eq!([60., 60., 61., 60., 60., 60., 61., 61.], note);
+    }
+}
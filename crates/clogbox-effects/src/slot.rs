@@ -0,0 +1,113 @@
+//! Real-time safe hand-over of shared values to the audio thread.
+//!
+//! Modules holding large, immutable data (sample buffers, impulse responses, ...) receive it
+//! through a [`Slot`]: the data is prepared and allocated on another thread, then stored in the
+//! slot, from which the module picks it up without blocking. Values replaced by the module are
+//! handed back to the slot, so that they are deallocated outside the audio thread on the next call
+//! to [`Slot::store`] or [`Slot::collect`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_effects::slot::Slot;
+//!
+//! let slot = Slot::<Vec<f32>>::new();
+//! slot.store(vec![0.0f32; 1024]);
+//! ```
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug)]
+pub(crate) struct SlotState<V> {
+    pub(crate) pending: Option<Arc<V>>,
+    pub(crate) retired: Option<Arc<V>>,
+}
+
+/// A shared slot handing values over to modules running on the audio thread.
+///
+/// The audio thread never blocks on the slot, and never deallocates values: replaced values are
+/// kept in the slot until they are collected from another thread.
+///
+/// A pending value is taken by the first module fetching it, so each slot should feed a single
+/// module; the same value can still be stored in several slots, as it is shared.
+#[derive(Debug)]
+pub struct Slot<V>(pub(crate) Arc<Mutex<SlotState<V>>>);
+
+impl<V> Clone for Slot<V> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<V> Default for Slot<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Slot<V> {
+    /// Creates a new, empty slot.
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(SlotState {
+            pending: None,
+            retired: None,
+        })))
+    }
+
+    /// Stores a new value in the slot, to be picked up by the module using it. Values retired by
+    /// the audio thread are deallocated.
+    pub fn store(&self, value: impl Into<Arc<V>>) {
+        let (pending, retired) = {
+            let mut state = self.0.lock().unwrap();
+            let pending = state.pending.replace(value.into());
+            (pending, state.retired.take())
+        };
+        drop((pending, retired));
+    }
+
+    /// Deallocates the values which were retired by the audio thread.
+    pub fn collect(&self) {
+        let retired = self.0.lock().unwrap().retired.take();
+        drop(retired);
+    }
+
+    /// Swaps the value in `current` with the pending value of the slot, if there is one. This
+    /// never blocks, and leaves `current` untouched if the slot is in use or if the previously
+    /// retired value has not been collected yet.
+    pub(crate) fn fetch(&self, current: &mut Option<Arc<V>>) {
+        let Ok(mut state) = self.0.try_lock() else {
+            return;
+        };
+        if state.pending.is_none() || state.retired.is_some() {
+            return;
+        }
+        let pending = state.pending.take();
+        state.retired = std::mem::replace(current, pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_retired_values_wait_for_collection() {
+        let slot = Slot::new();
+        let mut current = None;
+        let first = Arc::new(1);
+        slot.store(first.clone());
+        slot.fetch(&mut current);
+        assert_eq!(Some(1), current.as_deref().copied());
+
+        slot.store(2);
+        slot.fetch(&mut current);
+        assert_eq!(Some(2), current.as_deref().copied());
+        assert_eq!(2, Arc::strong_count(&first));
+
+        // Storing a new value deallocates the retired one
+        slot.store(3);
+        assert_eq!(1, Arc::strong_count(&first));
+        slot.fetch(&mut current);
+        assert_eq!(Some(3), current.as_deref().copied());
+    }
+}
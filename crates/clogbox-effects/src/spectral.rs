@@ -0,0 +1,286 @@
+//! Spectral processing with the short-time Fourier transform (STFT).
+//!
+//! This module provides [`StftModule`], which takes care of windowing, FFTs and overlap-add, and
+//! calls a user-provided closure on each spectral frame. This lets spectral effects (gates,
+//! freezes, filters, ...) be written as a function of the spectrum only.
+//!
+//! Frames are analyzed and resynthesized with a square-root Hann window, so that an unmodified
+//! spectrum reconstructs the input perfectly, delayed by the latency of the module (the frame size
+//! minus one sample).
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_effects::spectral::StftModule;
+//!
+//! let stream_data = StreamData::new(44100.0, 120.0, 512);
+//! // Spectral gate, removing bins below a fixed magnitude
+//! let mut gate = StftModule::<f32, _>::new(1024, 4, |spectrum| {
+//!     for bin in spectrum {
+//!         if bin.norm() < 0.1 {
+//!             *bin = Default::default();
+//!         }
+//!     }
+//! });
+//! let input = [0.0; 512];
+//! let mut output = [0.0; 512];
+//! gate.process(&stream_data, &[&input], &mut [&mut output]);
+//! assert_eq!(1023, gate.latency_samples());
+//! ```
+use az::CastFrom;
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::Sequential;
+use num_complex::Complex;
+use num_traits::{Float, FloatConst, Zero};
+use rustfft::{Fft, FftNum, FftPlanner};
+use std::fmt;
+use std::sync::Arc;
+use typenum::U1;
+
+/// A module processing its input in the frequency domain, by calling a closure on each frame of
+/// the short-time Fourier transform of the signal.
+///
+/// The closure receives the positive-frequency half of the spectrum (`frame_size / 2 + 1`
+/// bins, from DC to Nyquist); the negative frequencies are reconstructed from it so that the output
+/// stays real.
+pub struct StftModule<T: FftNum, F> {
+    frame_size: usize,
+    hop_size: usize,
+    window: Box<[T]>,
+    input: Box<[T]>,
+    output: Box<[T]>,
+    accumulator: Box<[T]>,
+    spectrum: Box<[Complex<T>]>,
+    scratch: Box<[Complex<T>]>,
+    forward: Arc<dyn Fft<T>>,
+    inverse: Arc<dyn Fft<T>>,
+    position: usize,
+    process_frame: F,
+}
+
+impl<T: FftNum, F> fmt::Debug for StftModule<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StftModule")
+            .field("frame_size", &self.frame_size)
+            .field("hop_size", &self.hop_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: FftNum + Float + FloatConst + CastFrom<f64>, F: FnMut(&mut [Complex<T>])> StftModule<T, F> {
+    /// Creates a new STFT module.
+    ///
+    /// # Arguments
+    ///
+    /// * `frame_size` - Size of the analysis frames, in samples. Must be even.
+    /// * `overlap` - Number of frames overlapping each sample, at least 2. The hop size between
+    ///   frames is `frame_size / overlap`.
+    /// * `process_frame` - Closure called on the spectrum of each frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the frame size is odd, or if it is not divisible by the overlap.
+    pub fn new(frame_size: usize, overlap: usize, process_frame: F) -> Self {
+        assert!(frame_size % 2 == 0, "STFT frame size must be even");
+        assert!(overlap >= 2, "STFT overlap must be at least 2");
+        assert!(
+            frame_size % overlap == 0,
+            "STFT frame size must be divisible by the overlap"
+        );
+        let mut planner = FftPlanner::new();
+        let forward = planner.plan_fft_forward(frame_size);
+        let inverse = planner.plan_fft_inverse(frame_size);
+        let scratch_len = forward
+            .get_inplace_scratch_len()
+            .max(inverse.get_inplace_scratch_len());
+
+        // Square-root periodic Hann window, so that analysis and synthesis windows multiply into a
+        // Hann window, which sums to `overlap / 2` when overlapped
+        let window = (0..frame_size)
+            .map(|i| {
+                let phase = std::f64::consts::TAU * i as f64 / frame_size as f64;
+                T::cast_from((0.5 - 0.5 * phase.cos()).sqrt())
+            })
+            .collect();
+
+        Self {
+            frame_size,
+            hop_size: frame_size / overlap,
+            window,
+            input: vec![T::zero(); frame_size].into_boxed_slice(),
+            output: vec![T::zero(); frame_size].into_boxed_slice(),
+            accumulator: vec![T::zero(); frame_size].into_boxed_slice(),
+            spectrum: vec![Complex::zero(); frame_size].into_boxed_slice(),
+            scratch: vec![Complex::zero(); scratch_len].into_boxed_slice(),
+            forward,
+            inverse,
+            position: frame_size - frame_size / overlap,
+            process_frame,
+        }
+    }
+
+    /// Returns the size of the analysis frames, in samples.
+    pub fn frame_size(&self) -> usize {
+        self.frame_size
+    }
+
+    /// Returns the number of samples between the start of two consecutive frames.
+    pub fn hop_size(&self) -> usize {
+        self.hop_size
+    }
+
+    /// Returns the latency introduced by the module, in samples.
+    ///
+    /// The output of a frame is available as soon as its last input sample is received, and the
+    /// first output sample of the frame corresponds to its first input sample.
+    pub fn latency_samples(&self) -> usize {
+        self.frame_size - 1
+    }
+
+    fn process_frame(&mut self) {
+        let n = self.frame_size;
+        for ((bin, &x), &w) in self
+            .spectrum
+            .iter_mut()
+            .zip(&*self.input)
+            .zip(&*self.window)
+        {
+            *bin = Complex::new(x * w, T::zero());
+        }
+        self.forward
+            .process_with_scratch(&mut self.spectrum, &mut self.scratch);
+
+        (self.process_frame)(&mut self.spectrum[..=n / 2]);
+        // Restore the Hermitian symmetry of the spectrum, as the closure only edits one half
+        self.spectrum[0].im = T::zero();
+        self.spectrum[n / 2].im = T::zero();
+        for k in 1..n / 2 {
+            self.spectrum[n - k] = self.spectrum[k].conj();
+        }
+
+        self.inverse
+            .process_with_scratch(&mut self.spectrum, &mut self.scratch);
+        let overlap = T::cast_from((n / self.hop_size) as f64);
+        let scale = T::cast_from(2.0) / (overlap * T::cast_from(n as f64));
+        for ((acc, bin), &w) in self
+            .accumulator
+            .iter_mut()
+            .zip(&*self.spectrum)
+            .zip(&*self.window)
+        {
+            *acc = *acc + bin.re * w * scale;
+        }
+
+        let hop = self.hop_size;
+        self.output[..hop].copy_from_slice(&self.accumulator[..hop]);
+        self.accumulator.copy_within(hop.., 0);
+        self.accumulator[n - hop..].fill(T::zero());
+        self.input.copy_within(hop.., 0);
+    }
+}
+
+impl<
+        T: 'static + Send + FftNum + Float + FloatConst + CastFrom<f64>,
+        F: 'static + Send + FnMut(&mut [Complex<T>]),
+    > Module for StftModule<T, F>
+{
+    type Sample = T;
+    type Inputs = Sequential<U1>;
+    type Outputs = Sequential<U1>;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reset(&mut self) {
+        self.input.fill(T::zero());
+        self.output.fill(T::zero());
+        self.accumulator.fill(T::zero());
+        self.position = self.frame_size - self.hop_size;
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        let latency = self.latency_samples() as f64;
+        EnumMapArray::new(|i| input_latencies[i] + latency)
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        let start = self.frame_size - self.hop_size;
+        for (out, &x) in outputs[0].iter_mut().zip(inputs[0]) {
+            self.input[self.position] = x;
+            self.position += 1;
+            if self.position == self.frame_size {
+                self.position = start;
+                self.process_frame();
+            }
+            *out = self.output[self.position - start];
+        }
+        ProcessStatus::Tail(self.frame_size as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    const STREAM_DATA: StreamData = StreamData::new(1000., 120., 100);
+
+    fn process<F: 'static + Send + FnMut(&mut [Complex<f64>])>(
+        module: &mut StftModule<f64, F>,
+        input: &[f64],
+    ) -> Vec<f64> {
+        let mut output = vec![0.; input.len()];
+        // Process in odd-sized blocks, to check independence from the frame boundaries
+        for (input, output) in input.chunks(37).zip(output.chunks_mut(37)) {
+            module.process(&STREAM_DATA, &[input], &mut [output]);
+        }
+        output
+    }
+
+    #[rstest]
+    #[case(64, 2)]
+    #[case(64, 4)]
+    #[case(256, 8)]
+    fn test_identity_reconstruction(#[case] frame_size: usize, #[case] overlap: usize) {
+        let mut module = StftModule::new(frame_size, overlap, |_| {});
+        let latency = module.latency_samples();
+        assert_eq!(frame_size - 1, latency);
+
+        let input = Vec::from_iter((0..1000).map(|i| (i as f64 * 0.05).sin() + 0.25));
+        let output = process(&mut module, &input);
+        assert!(output[..latency].iter().all(|&x| x.abs() < 1e-12));
+        // The first frame is only partially overlapped, and is skipped
+        for (expected, actual) in input.iter().zip(&output[latency..]).skip(frame_size) {
+            assert_relative_eq!(*expected, *actual, epsilon = 1e-9);
+        }
+    }
+
+    #[rstest]
+    fn test_spectral_processing() {
+        let mut module = StftModule::new(64, 4, |spectrum: &mut [Complex<f64>]| {
+            spectrum.fill(Complex::zero());
+        });
+        let output = process(&mut module, &[1.; 500]);
+        assert!(output.iter().all(|&x| x.abs() < 1e-12));
+    }
+
+    #[rstest]
+    fn test_latency() {
+        let module = StftModule::<f32, _>::new(1024, 4, |_| {});
+        let latency = module.latency(EnumMapArray::new(|_| 10.));
+        assert_eq!([1033.], latency.into_inner().into_array());
+    }
+}
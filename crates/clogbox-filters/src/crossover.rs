@@ -0,0 +1,506 @@
+//! Linkwitz-Riley crossovers, splitting a signal into frequency bands.
+//!
+//! A 4th order Linkwitz-Riley crossover is made of two cascaded 2nd order Butterworth filters per
+//! band. Its bands sum back to an allpass-filtered version of the input, which means that the
+//! bands can be processed separately (e.g. in multiband compressors or distortions) and then
+//! summed without any coloration of the magnitude response.
+//!
+//! The 2nd order sections are implemented with the trapezoidal SVF topology (see the [`svf`]
+//! module), and share their coefficients so that the reconstruction holds at every frequency.
+//!
+//! [`svf`]: crate::svf
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_filters::crossover::LinkwitzRiley;
+//!
+//! let mut crossover = LinkwitzRiley::new(44100.0, 1000.0);
+//! let (low, high) = crossover.split(1.0f32);
+//! ```
+
+use az::CastFrom;
+use clogbox_core::module::sample::SampleModule;
+use clogbox_core::module::{ProcessStatus, StreamData};
+use clogbox_core::param::value::Value;
+use clogbox_core::param::{GetParameter, SetParameter};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::{seq, Sequential};
+use clogbox_derive::Enum;
+use num_traits::{Float, FloatConst};
+use numeric_literals::replace_float_literals;
+use typenum::U1;
+
+/// Trapezoidal SVF section with a Butterworth response, the building block of the crossovers.
+#[derive(Debug, Copy, Clone)]
+struct ButterworthSection<T> {
+    s: [T; 2],
+    a: [T; 3],
+}
+
+impl<T: Float + FloatConst + CastFrom<f64>> ButterworthSection<T> {
+    /// Damping of a 2nd order Butterworth filter (1 / Q).
+    const K: f64 = std::f64::consts::SQRT_2;
+
+    #[replace_float_literals(T::cast_from(literal))]
+    fn new(g: T) -> Self {
+        let mut this = Self {
+            s: [0.; 2],
+            a: [0.; 3],
+        };
+        this.set_g(g);
+        this
+    }
+
+    #[replace_float_literals(T::cast_from(literal))]
+    fn set_g(&mut self, g: T) {
+        let k = T::cast_from(Self::K);
+        let a1 = (1. + g * (g + k)).recip();
+        let a2 = g * a1;
+        self.a = [a1, a2, g * a2];
+    }
+
+    #[replace_float_literals(T::cast_from(literal))]
+    fn reset(&mut self) {
+        self.s = [0.; 2];
+    }
+
+    /// Processes a single sample, returning the lowpass, bandpass and highpass outputs.
+    #[inline]
+    #[replace_float_literals(T::cast_from(literal))]
+    fn process(&mut self, x: T) -> (T, T, T) {
+        let [s1, s2] = self.s;
+        let [a1, a2, a3] = self.a;
+        let v3 = x - s2;
+        let bp = a1 * s1 + a2 * v3;
+        let lp = s2 + a2 * s1 + a3 * v3;
+        self.s = [2. * bp - s1, 2. * lp - s2];
+        let hp = x - T::cast_from(Self::K) * bp - lp;
+        (lp, bp, hp)
+    }
+
+    /// Processes a single sample through the allpass response of this section.
+    #[inline]
+    #[replace_float_literals(T::cast_from(literal))]
+    fn allpass(&mut self, x: T) -> T {
+        let (_, bp, _) = self.process(x);
+        x - 2. * T::cast_from(Self::K) * bp
+    }
+}
+
+/// Computes the prewarped integrator gain for the given cutoff frequency, keeping the cutoff
+/// below the Nyquist frequency.
+#[replace_float_literals(T::cast_from(literal))]
+fn prewarp<T: Float + FloatConst + CastFrom<f64>>(sample_rate: T, cutoff: T) -> T {
+    let cutoff = cutoff.max(1.).min(0.49 * sample_rate);
+    (T::PI() * cutoff / sample_rate).tan()
+}
+
+/// 4th order Linkwitz-Riley crossover, splitting a signal into a low and a high band.
+#[derive(Debug, Copy, Clone)]
+pub struct LinkwitzRiley<T> {
+    lowpass: [ButterworthSection<T>; 2],
+    highpass: [ButterworthSection<T>; 2],
+    sample_rate: T,
+    cutoff: T,
+}
+
+impl<T: Float + FloatConst + CastFrom<f64>> LinkwitzRiley<T> {
+    /// Creates a new crossover with the given sample rate and crossover frequency (in Hz).
+    pub fn new(sample_rate: T, cutoff: T) -> Self {
+        let section = ButterworthSection::new(prewarp(sample_rate, cutoff));
+        Self {
+            lowpass: [section; 2],
+            highpass: [section; 2],
+            sample_rate,
+            cutoff,
+        }
+    }
+
+    /// Returns the crossover frequency (in Hz).
+    pub fn cutoff(&self) -> T {
+        self.cutoff
+    }
+
+    /// Sets the crossover frequency (in Hz).
+    pub fn set_cutoff(&mut self, cutoff: T) {
+        self.cutoff = cutoff;
+        self.update_coefficients();
+    }
+
+    /// Sets the sample rate of the crossover.
+    pub fn set_sample_rate(&mut self, sample_rate: T) {
+        self.sample_rate = sample_rate;
+        self.update_coefficients();
+    }
+
+    /// Resets the state of the crossover filters.
+    pub fn reset(&mut self) {
+        for section in self.lowpass.iter_mut().chain(&mut self.highpass) {
+            section.reset();
+        }
+    }
+
+    /// Splits a single sample into its low and high bands, returned in that order.
+    ///
+    /// The sum of the bands is the input filtered by a 2nd order allpass filter at the crossover
+    /// frequency, see [`LinkwitzRileyAllpass`].
+    #[inline]
+    pub fn split(&mut self, x: T) -> (T, T) {
+        let low = self
+            .lowpass
+            .iter_mut()
+            .fold(x, |x, section| section.process(x).0);
+        let high = self
+            .highpass
+            .iter_mut()
+            .fold(x, |x, section| section.process(x).2);
+        (low, high)
+    }
+
+    fn update_coefficients(&mut self) {
+        let g = prewarp(self.sample_rate, self.cutoff);
+        for section in self.lowpass.iter_mut().chain(&mut self.highpass) {
+            section.set_g(g);
+        }
+    }
+}
+
+/// Allpass filter matching the phase response of a [`LinkwitzRiley`] crossover.
+///
+/// This is used to align the phase of bands which did not go through a given crossover, so that
+/// all bands of a multi-way crossover sum back with a flat magnitude response.
+#[derive(Debug, Copy, Clone)]
+pub struct LinkwitzRileyAllpass<T> {
+    section: ButterworthSection<T>,
+    sample_rate: T,
+    cutoff: T,
+}
+
+impl<T: Float + FloatConst + CastFrom<f64>> LinkwitzRileyAllpass<T> {
+    /// Creates a new allpass filter with the given sample rate and crossover frequency (in Hz).
+    pub fn new(sample_rate: T, cutoff: T) -> Self {
+        Self {
+            section: ButterworthSection::new(prewarp(sample_rate, cutoff)),
+            sample_rate,
+            cutoff,
+        }
+    }
+
+    /// Sets the crossover frequency (in Hz).
+    pub fn set_cutoff(&mut self, cutoff: T) {
+        self.cutoff = cutoff;
+        self.section.set_g(prewarp(self.sample_rate, cutoff));
+    }
+
+    /// Sets the sample rate of the filter.
+    pub fn set_sample_rate(&mut self, sample_rate: T) {
+        self.sample_rate = sample_rate;
+        self.section.set_g(prewarp(sample_rate, self.cutoff));
+    }
+
+    /// Resets the state of the filter.
+    pub fn reset(&mut self) {
+        self.section.reset();
+    }
+
+    /// Filters a single sample.
+    #[inline]
+    pub fn process(&mut self, x: T) -> T {
+        self.section.allpass(x)
+    }
+}
+
+/// Outputs of a 2-way crossover.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum TwoBand {
+    /// Band below the crossover frequency.
+    Low,
+    /// Band above the crossover frequency.
+    High,
+}
+
+/// Parameters of a 2-way crossover.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum TwoWayParams {
+    /// Crossover frequency (Hz).
+    Frequency,
+}
+
+/// 2-way crossover module, splitting its input into a low and a high band.
+#[derive(Debug, Copy, Clone)]
+pub struct TwoWayCrossover<T> {
+    crossover: LinkwitzRiley<T>,
+    frequency: f32,
+}
+
+impl<T: Float + FloatConst + CastFrom<f64>> TwoWayCrossover<T> {
+    /// Creates a new 2-way crossover with the given sample rate and crossover frequency (in Hz).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_core::module::{Module, StreamData};
+    /// use clogbox_filters::crossover::TwoWayCrossover;
+    ///
+    /// let mut crossover = TwoWayCrossover::<f32>::new(44100.0, 200.0);
+    /// let stream_data = StreamData::new(44100.0, 120.0, 4);
+    /// let (mut low, mut high) = ([0.0; 4], [0.0; 4]);
+    /// crossover.process(&stream_data, &[&[1.0; 4]], &mut [&mut low, &mut high]);
+    /// ```
+    pub fn new(sample_rate: f64, frequency: f32) -> Self {
+        Self {
+            crossover: LinkwitzRiley::new(
+                T::cast_from(sample_rate),
+                T::cast_from(frequency as f64),
+            ),
+            frequency,
+        }
+    }
+
+    /// Sets the crossover frequency (in Hz).
+    pub fn set_frequency(&mut self, frequency: f32) {
+        self.frequency = frequency;
+        self.crossover.set_cutoff(T::cast_from(frequency as f64));
+    }
+}
+
+impl<T: 'static + Send + Float + FloatConst + CastFrom<f64>> SampleModule for TwoWayCrossover<T> {
+    type Sample = T;
+    type Inputs = Sequential<U1>;
+    type Outputs = TwoBand;
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.crossover
+            .set_sample_rate(T::cast_from(stream_data.sample_rate));
+    }
+
+    fn reset(&mut self) {
+        self.crossover.reset();
+    }
+
+    fn latency(
+        &self,
+        input_latency: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|_| input_latency[seq(0)])
+    }
+
+    fn process_sample(
+        &mut self,
+        _: &StreamData,
+        inputs: EnumMapArray<Self::Inputs, Self::Sample>,
+    ) -> (ProcessStatus, EnumMapArray<Self::Outputs, Self::Sample>) {
+        let (low, high) = self.crossover.split(inputs[seq(0)]);
+        (
+            ProcessStatus::Running,
+            EnumMapArray::from_array([low, high].into()),
+        )
+    }
+}
+
+impl<T> GetParameter for TwoWayCrossover<T> {
+    type Param = TwoWayParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            TwoWayParams::Frequency => Value::Float(self.frequency),
+        }
+    }
+}
+
+impl<T: Float + FloatConst + CastFrom<f64>> SetParameter for TwoWayCrossover<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        let Ok(value) = f32::try_from(value) else {
+            return;
+        };
+        match param {
+            TwoWayParams::Frequency => self.set_frequency(value),
+        }
+    }
+}
+
+/// Outputs of a 3-way crossover.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum ThreeBand {
+    /// Band below the low crossover frequency.
+    Low,
+    /// Band between the two crossover frequencies.
+    Mid,
+    /// Band above the high crossover frequency.
+    High,
+}
+
+/// Parameters of a 3-way crossover.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Enum)]
+pub enum ThreeWayParams {
+    /// Crossover frequency between the low and mid bands (Hz).
+    #[display = "Low frequency"]
+    LowFrequency,
+    /// Crossover frequency between the mid and high bands (Hz).
+    #[display = "High frequency"]
+    HighFrequency,
+}
+
+/// 3-way crossover module, splitting its input into low, mid and high bands.
+///
+/// The input is first split at the low crossover frequency, and the upper band is then split again
+/// at the high crossover frequency. The low band goes through a [`LinkwitzRileyAllpass`] matching
+/// the second crossover, so that the three bands sum back with a flat magnitude response.
+#[derive(Debug, Copy, Clone)]
+pub struct ThreeWayCrossover<T> {
+    low: LinkwitzRiley<T>,
+    high: LinkwitzRiley<T>,
+    compensation: LinkwitzRileyAllpass<T>,
+    frequencies: [f32; 2],
+}
+
+impl<T: Float + FloatConst + CastFrom<f64>> ThreeWayCrossover<T> {
+    /// Creates a new 3-way crossover with the given sample rate and crossover frequencies (in Hz).
+    pub fn new(sample_rate: f64, low_frequency: f32, high_frequency: f32) -> Self {
+        let sample_rate = T::cast_from(sample_rate);
+        let high = T::cast_from(high_frequency as f64);
+        Self {
+            low: LinkwitzRiley::new(sample_rate, T::cast_from(low_frequency as f64)),
+            high: LinkwitzRiley::new(sample_rate, high),
+            compensation: LinkwitzRileyAllpass::new(sample_rate, high),
+            frequencies: [low_frequency, high_frequency],
+        }
+    }
+
+    /// Sets the crossover frequency between the low and mid bands (in Hz).
+    pub fn set_low_frequency(&mut self, frequency: f32) {
+        self.frequencies[0] = frequency;
+        self.low.set_cutoff(T::cast_from(frequency as f64));
+    }
+
+    /// Sets the crossover frequency between the mid and high bands (in Hz).
+    pub fn set_high_frequency(&mut self, frequency: f32) {
+        self.frequencies[1] = frequency;
+        let frequency = T::cast_from(frequency as f64);
+        self.high.set_cutoff(frequency);
+        self.compensation.set_cutoff(frequency);
+    }
+}
+
+impl<T: 'static + Send + Float + FloatConst + CastFrom<f64>> SampleModule for ThreeWayCrossover<T> {
+    type Sample = T;
+    type Inputs = Sequential<U1>;
+    type Outputs = ThreeBand;
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        let sample_rate = T::cast_from(stream_data.sample_rate);
+        self.low.set_sample_rate(sample_rate);
+        self.high.set_sample_rate(sample_rate);
+        self.compensation.set_sample_rate(sample_rate);
+    }
+
+    fn reset(&mut self) {
+        self.low.reset();
+        self.high.reset();
+        self.compensation.reset();
+    }
+
+    fn latency(
+        &self,
+        input_latency: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        EnumMapArray::new(|_| input_latency[seq(0)])
+    }
+
+    fn process_sample(
+        &mut self,
+        _: &StreamData,
+        inputs: EnumMapArray<Self::Inputs, Self::Sample>,
+    ) -> (ProcessStatus, EnumMapArray<Self::Outputs, Self::Sample>) {
+        let (low, rest) = self.low.split(inputs[seq(0)]);
+        let (mid, high) = self.high.split(rest);
+        let low = self.compensation.process(low);
+        (
+            ProcessStatus::Running,
+            EnumMapArray::from_array([low, mid, high].into()),
+        )
+    }
+}
+
+impl<T> GetParameter for ThreeWayCrossover<T> {
+    type Param = ThreeWayParams;
+
+    fn get_param_raw(&self, param: Self::Param) -> Value<'_> {
+        match param {
+            ThreeWayParams::LowFrequency => Value::Float(self.frequencies[0]),
+            ThreeWayParams::HighFrequency => Value::Float(self.frequencies[1]),
+        }
+    }
+}
+
+impl<T: Float + FloatConst + CastFrom<f64>> SetParameter for ThreeWayCrossover<T> {
+    fn set_param_raw(&mut self, param: Self::Param, value: Value) {
+        let Ok(value) = f32::try_from(value) else {
+            return;
+        };
+        match param {
+            ThreeWayParams::LowFrequency => self.set_low_frequency(value),
+            ThreeWayParams::HighFrequency => self.set_high_frequency(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    const SAMPLE_RATE: f64 = 48000.;
+    const STREAM_DATA: StreamData = StreamData::new(SAMPLE_RATE, 120., 1);
+
+    fn noise(len: usize) -> impl Iterator<Item = f64> {
+        let mut state = 0x1234_5678u32;
+        (0..len).map(move |_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f64 / u32::MAX as f64 * 2. - 1.
+        })
+    }
+
+    #[rstest]
+    #[case(100.)]
+    #[case(1000.)]
+    #[case(15000.)]
+    fn test_two_way_sums_to_allpass(#[case] frequency: f64) {
+        let mut crossover = LinkwitzRiley::new(SAMPLE_RATE, frequency);
+        let mut allpass = LinkwitzRileyAllpass::new(SAMPLE_RATE, frequency);
+        for x in noise(1024) {
+            let (low, high) = crossover.split(x);
+            assert_relative_eq!(allpass.process(x), low + high, epsilon = 1e-9);
+        }
+    }
+
+    #[rstest]
+    fn test_three_way_sums_to_allpass() {
+        let mut crossover = ThreeWayCrossover::<f64>::new(SAMPLE_RATE, 200., 3000.);
+        let mut allpass = [
+            LinkwitzRileyAllpass::new(SAMPLE_RATE, 200.),
+            LinkwitzRileyAllpass::new(SAMPLE_RATE, 3000.),
+        ];
+        for x in noise(1024) {
+            let (_, bands) = crossover.process_sample(&STREAM_DATA, EnumMapArray::new(|_| x));
+            let expected = allpass.iter_mut().fold(x, |x, ap| ap.process(x));
+            let sum = bands[ThreeBand::Low] + bands[ThreeBand::Mid] + bands[ThreeBand::High];
+            assert_relative_eq!(expected, sum, epsilon = 1e-9);
+        }
+    }
+
+    #[rstest]
+    fn test_dc_goes_to_low_band() {
+        let mut crossover = TwoWayCrossover::<f64>::new(SAMPLE_RATE, 1000.);
+        let mut bands = EnumMapArray::new(|_| 0.);
+        for _ in 0..4800 {
+            (_, bands) = crossover.process_sample(&STREAM_DATA, EnumMapArray::new(|_| 1.));
+        }
+        assert_relative_eq!(1., bands[TwoBand::Low], epsilon = 1e-6);
+        assert_relative_eq!(0., bands[TwoBand::High], epsilon = 1e-6);
+    }
+}
@@ -0,0 +1,148 @@
+//! DC blocking filter.
+//!
+//! Feedback paths and asymmetric saturators can build up a DC offset in the signal, which eats into
+//! the headroom of later stages. [`DcBlock`] removes it with a one-pole highpass filter at a very
+//! low cutoff frequency.
+//!
+//! # Example
+//!
+//! ```rust
+//! use clogbox_core::module::{Module, StreamData};
+//! use clogbox_core::r#enum::Sequential;
+//! use clogbox_filters::dc_block::DcBlock;
+//! use typenum::U1;
+//!
+//! let stream_data = StreamData::new(44100.0, 120.0, 4);
+//! let mut dc_block = DcBlock::<f32, Sequential<U1>>::new(stream_data.sample_rate);
+//! let mut output = [0.0; 4];
+//! dc_block.process(&stream_data, &[&[1.0; 4]], &mut [&mut output]);
+//! assert_eq!(1.0, output[0]);
+//! assert!(output[3] < 1.0);
+//! ```
+use az::CastFrom;
+use clogbox_core::math::dsp::flush_denormal;
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::r#enum::enum_map::EnumMapArray;
+use clogbox_core::r#enum::{enum_iter, Enum};
+use num_traits::Float;
+
+/// A DC blocking filter, applied to all of its channels.
+///
+/// The filter is the classic one-pole, one-zero highpass `y[n] = x[n] - x[n-1] + R y[n-1]`. Its
+/// state is flushed of subnormal values, so that it can safely be placed in feedback paths.
+#[derive(Debug, Clone)]
+pub struct DcBlock<T, E: Enum> {
+    state: EnumMapArray<E, [T; 2]>,
+    coefficient: T,
+    cutoff: f32,
+}
+
+impl<T: Float + CastFrom<f64>, E: Enum> DcBlock<T, E> {
+    /// Default cutoff frequency of the filter (Hz).
+    pub const DEFAULT_CUTOFF: f32 = 5.;
+
+    /// Creates a new DC blocker with the default cutoff frequency.
+    pub fn new(sample_rate: f64) -> Self {
+        Self::with_cutoff(sample_rate, Self::DEFAULT_CUTOFF)
+    }
+
+    /// Creates a new DC blocker with the given cutoff frequency (in Hz).
+    pub fn with_cutoff(sample_rate: f64, cutoff: f32) -> Self {
+        let mut this = Self {
+            state: EnumMapArray::new(|_| [T::zero(); 2]),
+            coefficient: T::zero(),
+            cutoff,
+        };
+        this.set_sample_rate(sample_rate);
+        this
+    }
+
+    /// Returns the cutoff frequency of the filter (Hz).
+    pub fn cutoff(&self) -> f32 {
+        self.cutoff
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f64) {
+        let w = std::f64::consts::TAU * self.cutoff as f64 / sample_rate;
+        self.coefficient = T::cast_from((-w).exp());
+    }
+}
+
+impl<T: 'static + Send + Float + CastFrom<f64>, E: 'static + Enum> Module for DcBlock<T, E> {
+    type Sample = T;
+    type Inputs = E;
+    type Outputs = E;
+
+    fn supports_stream(&self, _: StreamData) -> bool {
+        true
+    }
+
+    fn reallocate(&mut self, stream_data: StreamData) {
+        self.set_sample_rate(stream_data.sample_rate);
+    }
+
+    fn reset(&mut self) {
+        for e in enum_iter::<E>() {
+            self.state[e] = [T::zero(); 2];
+        }
+    }
+
+    fn latency(
+        &self,
+        input_latencies: EnumMapArray<Self::Inputs, f64>,
+    ) -> EnumMapArray<Self::Outputs, f64> {
+        input_latencies
+    }
+
+    #[profiling::function]
+    fn process(
+        &mut self,
+        _: &StreamData,
+        inputs: &[&[Self::Sample]],
+        outputs: &mut [&mut [Self::Sample]],
+    ) -> ProcessStatus {
+        for e in enum_iter::<E>() {
+            let [mut x1, mut y1] = self.state[e];
+            for (out, &x) in outputs[e.cast()].iter_mut().zip(inputs[e.cast()]) {
+                y1 = flush_denormal(x - x1 + self.coefficient * y1);
+                x1 = x;
+                *out = y1;
+            }
+            self.state[e] = [x1, y1];
+        }
+        ProcessStatus::Running
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use clogbox_core::r#enum::Sequential;
+    use rstest::rstest;
+    use typenum::U1;
+
+    const STREAM_DATA: StreamData = StreamData::new(1000., 120., 1000);
+
+    fn process(input: &[f64]) -> Vec<f64> {
+        let mut dc_block = DcBlock::<f64, Sequential<U1>>::new(STREAM_DATA.sample_rate);
+        let mut output = vec![0.; input.len()];
+        dc_block.process(&STREAM_DATA, &[input], &mut [&mut output]);
+        output
+    }
+
+    #[rstest]
+    fn test_removes_offset() {
+        let output = process(&[0.5; 1000]);
+        assert_eq!(0.5, output[0]);
+        assert_relative_eq!(0., output[999], epsilon = 1e-9);
+    }
+
+    #[rstest]
+    fn test_passes_high_frequencies() {
+        let input = Vec::from_iter((0..1000).map(|i| if i % 2 == 0 { 1. } else { -1. }));
+        let output = process(&input);
+        let expected = 2. / (1. + (-std::f64::consts::TAU * 5e-3).exp());
+        assert_relative_eq!(expected, output[998], epsilon = 1e-6);
+    }
+}
@@ -4,14 +4,17 @@
 //! This module provides a number of non-linear filters that can be used to modify the
 //! amplitude of audio signals.
 use std::marker::PhantomData;
-use generic_array::ArrayLength;
-use num_traits::Float;
+use az::CastFrom;
+use num_traits::{Float, FloatConst, ToPrimitive};
+use numeric_literals::replace_float_literals;
 use typenum::U1;
-use clogbox_core::module::{Module, ProcessStatus, RawModule, StreamData};
-use clogbox_core::module::sample::{SampleContext, SampleModule};
-use clogbox_core::r#enum::{seq, Sequential, Enum};
+use clogbox_core::module::{Module, ProcessStatus, StreamData};
+use clogbox_core::module::sample::SampleModule;
+use clogbox_core::r#enum::{seq, Sequential};
 use clogbox_core::r#enum::enum_map::EnumMapArray;
 
+pub mod crossover;
+pub mod dc_block;
 pub mod svf;
 
 /// A trait representing a saturator that can saturate mono signals.
@@ -174,4 +177,220 @@ pub const fn asinh<T: Float>() -> Memoryless<T, fn(T) -> T> {
 /// A `Memoryless` instance that clamps input values.
 pub fn hard_clip<T: Float>(min: T, max: T) -> Memoryless<T, impl Copy + Fn(T) -> T> {
     Memoryless::new(move |x: T| x.clamp(min, max))
-}
\ No newline at end of file
+}
+
+/// A [`Saturator`] which amplifies the signal by a drive amount before saturating it.
+///
+/// Optionally, a makeup gain can be applied after saturation to compensate for the change in
+/// loudness introduced by the drive, so that different drive amounts can be compared at matched
+/// levels. The makeup gain is calibrated by running a reference sine wave through a copy of the
+/// inner saturator, which makes it work with any saturator. Calibration is done once for a table
+/// of drive amounts when gain compensation is enabled, so that changing the drive afterwards only
+/// interpolates the table, and can be automated.
+#[derive(Debug, Copy, Clone)]
+pub struct Driven<S: Saturator> {
+    saturator: S,
+    drive: S::Sample,
+    makeup: S::Sample,
+    makeup_table: [S::Sample; DRIVEN_TABLE_LEN],
+    gain_compensation: bool,
+}
+
+/// Number of drive amounts for which the makeup gain of [`Driven`] is calibrated.
+const DRIVEN_TABLE_LEN: usize = 25;
+
+impl<S: Clone + Saturator<Sample: Float + FloatConst + CastFrom<f64>>> Driven<S> {
+    /// Amplitude of the sine wave used to calibrate the makeup gain (about -12 dBFS).
+    const REFERENCE_AMPLITUDE: f64 = 0.25;
+    /// Number of samples of the single sine period used to calibrate the makeup gain.
+    const REFERENCE_LENGTH: usize = 64;
+    /// Lowest drive amount of the makeup gain table, in dB.
+    const TABLE_MIN_DB: f64 = -24.0;
+    /// Spacing between the drive amounts of the makeup gain table, in dB. With
+    /// [`DRIVEN_TABLE_LEN`] entries, the table goes up to +48 dB.
+    const TABLE_STEP_DB: f64 = 3.0;
+
+    /// Creates a new [`Driven`] saturator with the given drive amount (as a linear gain).
+    ///
+    /// Gain compensation is disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use clogbox_filters::{tanh, Driven, Saturator};
+    ///
+    /// let mut saturator = Driven::new(tanh::<f32>(), 4.0).with_gain_compensation(true);
+    /// let y = saturator.saturate(0.1);
+    /// ```
+    pub fn new(saturator: S, drive: S::Sample) -> Self {
+        let mut this = Self {
+            saturator,
+            drive,
+            makeup: S::Sample::cast_from(1.0),
+            makeup_table: [S::Sample::cast_from(1.0); DRIVEN_TABLE_LEN],
+            gain_compensation: false,
+        };
+        this.update_makeup();
+        this
+    }
+
+    /// Returns the current drive amount.
+    pub fn drive(&self) -> S::Sample {
+        self.drive
+    }
+
+    /// Sets the drive amount (as a linear gain).
+    ///
+    /// This does not allocate nor run the calibration, and can be called from the audio thread.
+    pub fn set_drive(&mut self, drive: S::Sample) {
+        self.drive = drive;
+        self.update_makeup();
+    }
+
+    /// Returns whether gain compensation is enabled.
+    pub fn gain_compensation(&self) -> bool {
+        self.gain_compensation
+    }
+
+    /// Enables or disables gain compensation. Enabling it calibrates the makeup gain table, which
+    /// runs the inner saturator for all drive amounts of the table.
+    pub fn set_gain_compensation(&mut self, enabled: bool) {
+        if enabled && !self.gain_compensation {
+            self.makeup_table = std::array::from_fn(|i| {
+                let drive_db = Self::TABLE_MIN_DB + Self::TABLE_STEP_DB * i as f64;
+                self.calibrate(S::Sample::cast_from(10f64.powf(drive_db / 20.0)))
+            });
+        }
+        self.gain_compensation = enabled;
+        self.update_makeup();
+    }
+
+    /// Enables or disables gain compensation, returning the modified saturator.
+    pub fn with_gain_compensation(mut self, enabled: bool) -> Self {
+        self.set_gain_compensation(enabled);
+        self
+    }
+
+    /// Returns the makeup gain currently applied after saturation. This is always 1 when gain
+    /// compensation is disabled.
+    pub fn makeup_gain(&self) -> S::Sample {
+        self.makeup
+    }
+
+    #[replace_float_literals(S::Sample::cast_from(literal))]
+    fn update_makeup(&mut self) {
+        if !self.gain_compensation || self.drive == 0.0 {
+            self.makeup = 1.0;
+            return;
+        }
+
+        // The table is calibrated for positive drives, the sign is assumed not to change loudness
+        let drive = self.drive.abs();
+        let last = DRIVEN_TABLE_LEN - 1;
+        let min_db = S::Sample::cast_from(Self::TABLE_MIN_DB);
+        let step_db = S::Sample::cast_from(Self::TABLE_STEP_DB);
+        let position = (20.0 * drive.log10() - min_db) / step_db;
+        self.makeup = if position <= 0.0 {
+            // Below the table, saturators are close to linear, and the makeup gain compensates the
+            // drive alone
+            let min_drive = 10.0.powf(min_db / 20.0);
+            self.makeup_table[0] * min_drive / drive
+        } else if position >= S::Sample::cast_from(last as f64) {
+            self.makeup_table[last]
+        } else {
+            let index = position.floor();
+            let t = position - index;
+            let index = index.to_usize().unwrap_or(0).min(last - 1);
+            // Makeup gains are interpolated logarithmically, which is exact for linear saturators
+            let (a, b) = (self.makeup_table[index], self.makeup_table[index + 1]);
+            a * (b / a).powf(t)
+        };
+    }
+
+    /// Computes the makeup gain matching the RMS level of the reference sine wave before and after
+    /// saturation at the given drive amount.
+    #[replace_float_literals(S::Sample::cast_from(literal))]
+    fn calibrate(&self, drive: S::Sample) -> S::Sample {
+        let mut saturator = self.saturator.clone();
+        let amplitude = S::Sample::cast_from(Self::REFERENCE_AMPLITUDE);
+        let n = S::Sample::cast_from(Self::REFERENCE_LENGTH as f64);
+        let (sum_in, sum_out) = (0..Self::REFERENCE_LENGTH)
+            .map(|i| {
+                let phase = S::Sample::TAU() * S::Sample::cast_from(i as f64) / n;
+                amplitude * phase.sin()
+            })
+            .fold((0.0, 0.0), |(sum_in, sum_out), x| {
+                let y = saturator.saturate(drive * x);
+                (sum_in + x * x, sum_out + y * y)
+            });
+        if sum_out > S::Sample::epsilon() {
+            (sum_in / sum_out).sqrt()
+        } else {
+            1.0
+        }
+    }
+}
+
+impl<S: Saturator<Sample: Copy + std::ops::Mul<Output = S::Sample>>> Saturator for Driven<S> {
+    type Sample = S::Sample;
+
+    #[inline]
+    fn saturate(&mut self, value: Self::Sample) -> Self::Sample {
+        self.saturator.saturate(self.drive * value) * self.makeup
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_driven_linear_compensation() {
+        let mut saturator = Driven::new(Linear::<f32>::default(), 4.0).with_gain_compensation(true);
+        assert_relative_eq!(0.25, saturator.makeup_gain(), epsilon = 1e-6);
+        assert_relative_eq!(0.1, saturator.saturate(0.1), epsilon = 1e-6);
+    }
+
+    #[rstest]
+    fn test_driven_no_compensation() {
+        let mut saturator = Driven::new(tanh::<f64>(), 4.0);
+        assert_eq!(1.0, saturator.makeup_gain());
+        assert_relative_eq!(0.4f64.tanh(), saturator.saturate(0.1));
+    }
+
+    #[rstest]
+    #[case(1.0)]
+    #[case(4.0)]
+    #[case(10.0)]
+    #[case(100.0)]
+    fn test_driven_tanh_compensation_matches_rms(#[case] drive: f64) {
+        let mut saturator = Driven::new(tanh::<f64>(), drive).with_gain_compensation(true);
+        // Differs from the calibration signal in its frequency and sampling
+        let input = Vec::from_iter(
+            (0..1000).map(|i| 0.25 * (std::f64::consts::TAU * 7.0 * i as f64 / 1000.0).sin()),
+        );
+        let mut output = vec![0.0; input.len()];
+        saturator.saturate_buffer(&input, &mut output);
+
+        let rms = |x: &[f64]| (x.iter().map(|x| x * x).sum::<f64>() / x.len() as f64).sqrt();
+        assert_relative_eq!(rms(&input), rms(&output), max_relative = 1e-2);
+    }
+
+    #[rstest]
+    #[case(0.01)]
+    #[case(0.3)]
+    #[case(5.0)]
+    #[case(37.0)]
+    #[case(1000.0)]
+    fn test_driven_makeup_table_matches_calibration(#[case] drive: f64) {
+        let mut saturator = Driven::new(tanh::<f64>(), 1.0).with_gain_compensation(true);
+        saturator.set_drive(drive);
+        assert_relative_eq!(
+            saturator.calibrate(drive),
+            saturator.makeup_gain(),
+            max_relative = 1e-2
+        );
+    }
+}
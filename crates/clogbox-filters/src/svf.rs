@@ -6,12 +6,11 @@
 use crate::{Linear, Saturator};
 use az::{Cast, CastFrom};
 use clogbox_core::module::analysis::{FreqAnalysis, Matrix};
-use clogbox_core::module::sample::{SampleContext, SampleModule};
+use clogbox_core::module::sample::SampleModule;
 use clogbox_core::module::{ProcessStatus, StreamData};
 use clogbox_core::r#enum::enum_map::EnumMapArray;
 use clogbox_core::r#enum::Enum;
 use clogbox_derive::Enum;
-use generic_array::ArrayLength;
 use num_complex::Complex;
 use num_traits::{Float, FloatConst, Num, Zero};
 use numeric_array::NumericArray;